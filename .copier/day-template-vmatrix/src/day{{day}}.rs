@@ -12,9 +12,10 @@ impl From<&Tile> for char {
 }
 
 pub fn generator(input: &str) -> VMatrix<Tile> {
-    VMatrix::from_chars(input, |pos, ch| match ch {
-        _ => panic!("Invalid tile definition {ch:?}"),
+    VMatrix::try_from_chars(input, |pos, ch| match ch {
+        _ => Err("unknown tile"),
     })
+    .unwrap_or_else(|err| panic!("{err}"))
 }
 
 pub fn part_1(input: &VMatrix<Tile>) -> usize {
@@ -1,15 +1,44 @@
-use std::collections::{BinaryHeap, HashMap, VecDeque};
-use std::hash::Hash;
+use std::collections::hash_map::{Entry, RandomState};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash};
 use std::ops::Add;
+use std::sync::Mutex;
+
+use num::{One, Zero};
+use rayon::prelude::*;
+
+use crate::common::graph::CycleFound;
+use crate::common::instrument::{Instrument, InstrumentStats};
 
 struct BfsCell<P, C> {
     parent: Option<P>,
     cost: C,
+    settled: bool,
+}
+
+// Follows the parents from the key back to a root. None if the key was never reached.
+fn path_through_parents<K, C>(cells: &HashMap<K, BfsCell<K, C>>, key: &K) -> Option<Vec<K>>
+where
+    K: PartialEq + Eq + Hash + Clone,
+{
+    let mut result = Vec::new();
+    let mut currently_at = Some(key);
+    while let Some(at) = currently_at {
+        result.push(at.clone());
+        currently_at = cells.get(at)?.parent.as_ref();
+    }
+    result.reverse();
+    Some(result)
 }
 
 pub struct LinearBfs<C> {
     cells: Vec<Option<BfsCell<usize, C>>>,
-    consider: VecDeque<usize>,
+    // As long as keys are pushed in non-decreasing cost order (which is always the case when all
+    // edges weigh the same) a plain FIFO is enough. Once that breaks we move to the heap.
+    fifo: Option<VecDeque<usize>>,
+    heap: Vec<usize>,
+    heap_positions: Vec<Option<usize>>,
+    instrument: Instrument,
 }
 
 impl<C> LinearBfs<C>
@@ -20,131 +49,343 @@ where
     pub fn new(size: usize) -> Self {
         Self {
             cells: std::iter::repeat_with(|| None).take(size).collect(),
-            consider: VecDeque::new(),
+            fifo: Some(VecDeque::new()),
+            heap: Vec::new(),
+            heap_positions: vec![None; size],
+            instrument: Instrument::new(),
         }
     }
 
+    pub fn stats(&self) -> Option<InstrumentStats> {
+        self.instrument.stats()
+    }
+
     pub fn cost(&self, key: usize) -> Option<&C> {
         self.cells[key].as_ref().map(|cell| &cell.cost)
     }
 
+    pub fn is_settled(&self, key: usize) -> bool {
+        self.cells[key].as_ref().is_some_and(|cell| cell.settled)
+    }
+
     pub fn add_root(&mut self, key: usize, cost: C) {
-        self.cells[key] = Some(BfsCell { parent: None, cost });
-        self.consider.push_back(key)
+        let old_cell = self.cells[key].replace(BfsCell {
+            parent: None,
+            cost,
+            settled: false,
+        });
+        if old_cell.is_none() {
+            self.instrument.on_discover();
+        }
+        self.push(key, old_cell.is_some());
     }
 
     pub fn consider_next(&mut self) -> Option<usize> {
-        self.consider.pop_front()
+        loop {
+            let key = if let Some(fifo) = self.fifo.as_mut() {
+                fifo.pop_front()?
+            } else {
+                self.heap_pop()?
+            };
+            let cell = self.cells[key].as_mut().unwrap();
+            if cell.settled {
+                // Can only happen in FIFO mode, where there is no decrease-key
+                self.instrument.on_stale_pop();
+                continue;
+            }
+            cell.settled = true;
+            self.instrument.on_pop();
+            return Some(key);
+        }
     }
 
     pub fn add_edge(&mut self, parent: usize, key: usize, additional_cost: C) -> bool {
         let new_cost = self.cost(parent).unwrap() + &additional_cost;
         if let Some(existing_cell) = &self.cells[key] {
-            // TODO: this may fail because I'm using VecDeque instead of a BinaryHeap
-            if new_cost < existing_cell.cost {
-                assert!(self.consider.contains(&key), "out of order");
-            } else {
+            if existing_cell.settled || existing_cell.cost <= new_cost {
                 return false;
             }
         }
-        self.consider.push_back(key);
-        self.cells[key] = Some(BfsCell {
+        let old_cell = self.cells[key].replace(BfsCell {
             parent: Some(parent),
             cost: new_cost,
+            settled: false,
         });
+        if old_cell.is_none() {
+            self.instrument.on_discover();
+        }
+        self.push(key, old_cell.is_some());
         true
     }
 
     pub fn path_to(&self, key: usize) -> Vec<usize> {
         let mut result = Vec::new();
-        let mut currentlly_at = Some(key);
-        while let Some(at) = currentlly_at {
+        let mut currently_at = Some(key);
+        while let Some(at) = currently_at {
             result.push(at);
-            currentlly_at = self.cells[at].as_ref().unwrap().parent;
+            currently_at = self.cells[at].as_ref().unwrap().parent;
         }
         result.reverse();
         result
     }
+
+    // `decreased` means the key was already known with a higher cost. Its older entry is still
+    // somewhere in the FIFO and is now cheaper than the entries before it, so the FIFO is no longer
+    // in order.
+    fn push(&mut self, key: usize, decreased: bool) {
+        if let Some(fifo) = self.fifo.as_mut() {
+            let in_order = !decreased
+                && fifo.back().is_none_or(|&back| {
+                    self.cells[back].as_ref().unwrap().cost
+                        <= self.cells[key].as_ref().unwrap().cost
+                });
+            if in_order {
+                fifo.push_back(key);
+                self.instrument.on_push();
+                return;
+            }
+            let fifo = self.fifo.take().unwrap();
+            for pending in fifo {
+                // Entries that don't make it into the heap are the stale ones
+                if self.is_settled(pending) || !self.heap_push_or_decrease(pending) {
+                    self.instrument.on_stale_pop();
+                }
+            }
+        }
+        if self.heap_push_or_decrease(key) {
+            self.instrument.on_push();
+        }
+    }
+
+    fn heap_less(&self, i: usize, j: usize) -> bool {
+        let [a, b] = [i, j].map(|pos| &self.cells[self.heap[pos]].as_ref().unwrap().cost);
+        a < b
+    }
+
+    fn heap_swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.heap_positions[self.heap[i]] = Some(i);
+        self.heap_positions[self.heap[j]] = Some(j);
+    }
+
+    fn heap_sift_up(&mut self, mut pos: usize) {
+        while 0 < pos {
+            let parent = (pos - 1) / 2;
+            if !self.heap_less(pos, parent) {
+                break;
+            }
+            self.heap_swap(pos, parent);
+            pos = parent;
+        }
+    }
+
+    fn heap_sift_down(&mut self, mut pos: usize) {
+        loop {
+            let mut smallest = pos;
+            for child in [2 * pos + 1, 2 * pos + 2] {
+                if child < self.heap.len() && self.heap_less(child, smallest) {
+                    smallest = child;
+                }
+            }
+            if smallest == pos {
+                break;
+            }
+            self.heap_swap(pos, smallest);
+            pos = smallest;
+        }
+    }
+
+    // Returns true if the key was not already in the heap
+    fn heap_push_or_decrease(&mut self, key: usize) -> bool {
+        let (pos, is_new) = if let Some(pos) = self.heap_positions[key] {
+            (pos, false)
+        } else {
+            self.heap.push(key);
+            self.heap_positions[key] = Some(self.heap.len() - 1);
+            (self.heap.len() - 1, true)
+        };
+        self.heap_sift_up(pos);
+        is_new
+    }
+
+    fn heap_pop(&mut self) -> Option<usize> {
+        let last = self.heap.len().checked_sub(1)?;
+        self.heap_swap(0, last);
+        let key = self.heap.pop().unwrap();
+        self.heap_positions[key] = None;
+        self.heap_sift_down(0);
+        Some(key)
+    }
 }
 
 #[derive(PartialEq)]
 struct HeapCell<K, C> {
     key: K,
     cost: C,
+    // Same as the cost, unless there is a heuristic in which case it is added here
+    priority: C,
 }
 
 impl<K: PartialEq, C: PartialEq> Eq for HeapCell<K, C> {}
 
 impl<K: PartialEq, C: PartialOrd + Eq> PartialOrd for HeapCell<K, C> {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        other.cost.partial_cmp(&self.cost)
+        other.priority.partial_cmp(&self.priority)
     }
 }
 
 impl<K: PartialEq, C: Ord> Ord for HeapCell<K, C> {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        other.cost.cmp(&self.cost)
+        other.priority.cmp(&self.priority)
     }
 }
 
-pub struct HashMapBfs<K, C> {
+pub struct HashMapBfs<K, C, H = fn(&K) -> C> {
     cells: HashMap<K, BfsCell<K, C>>,
     consider: BinaryHeap<HeapCell<K, C>>,
+    heuristic: Option<H>,
+    // Every parent that reaches the key with its best cost - not just the first one
+    all_parents: Option<HashMap<K, Vec<K>>>,
+    instrument: Instrument,
 }
 
-impl<K, C> HashMapBfs<K, C>
+impl<K, C, H> HashMapBfs<K, C, H>
 where
     K: PartialEq + Eq + Hash + Clone,
     for<'a> &'a C: Add<&'a C, Output = C>,
     C: PartialOrd<C> + Ord + Clone,
+    H: Fn(&K) -> C,
 {
+    // Turns the search into A*. The heuristic must never overestimate the remaining cost, and must
+    // be consistent (never drop by more than an edge's cost when crossing that edge) - otherwise
+    // keys may be settled before their best cost is found.
+    pub fn with_heuristic(heuristic: H) -> Self {
+        Self {
+            cells: HashMap::new(),
+            consider: BinaryHeap::new(),
+            heuristic: Some(heuristic),
+            all_parents: None,
+            instrument: Instrument::new(),
+        }
+    }
+
+    pub fn stats(&self) -> Option<InstrumentStats> {
+        self.instrument.stats()
+    }
+
+    pub fn with_all_parents(mut self) -> Self {
+        assert!(
+            self.cells.is_empty(),
+            "must be set before the search starts"
+        );
+        self.all_parents = Some(HashMap::new());
+        self
+    }
+
+    fn push(&mut self, key: K, cost: C) {
+        let priority = if let Some(heuristic) = self.heuristic.as_ref() {
+            &cost + &heuristic(&key)
+        } else {
+            cost.clone()
+        };
+        self.consider.push(HeapCell {
+            key,
+            cost,
+            priority,
+        });
+        self.instrument.on_push();
+    }
+
     pub fn cost(&self, key: &K) -> Option<&C> {
         self.cells.get(key).map(|cell| &cell.cost)
     }
 
     pub fn add_root(&mut self, key: K, cost: C) {
-        self.cells.insert(
+        let old_cell = self.cells.insert(
             key.clone(),
             BfsCell {
                 parent: None,
                 cost: cost.clone(),
+                settled: false,
             },
         );
-        self.consider.push(HeapCell { key, cost });
+        if old_cell.is_none() {
+            self.instrument.on_discover();
+        }
+        self.push(key, cost);
     }
 
     pub fn consider_next(&mut self) -> Option<K> {
-        self.consider.pop().map(|hc| hc.key)
+        while let Some(HeapCell { key, cost, .. }) = self.consider.pop() {
+            let cell = self.cells.get_mut(&key).unwrap();
+            // A key gets pushed again whenever a cheaper path to it is found, so older entries for
+            // it are stale and must be skipped.
+            if cell.settled || cell.cost < cost {
+                self.instrument.on_stale_pop();
+                continue;
+            }
+            cell.settled = true;
+            self.instrument.on_pop();
+            return Some(key);
+        }
+        None
+    }
+
+    pub fn is_settled(&self, key: &K) -> bool {
+        self.cells.get(key).is_some_and(|cell| cell.settled)
+    }
+
+    // A lower bound for the cost of the next key to be considered (exact, unless the top of the
+    // heap is a stale entry). Only without a heuristic - with one, the heap is ordered by cost
+    // plus heuristic, so a cheaper key may still be pending.
+    pub fn peek_cost(&self) -> Option<&C> {
+        self.consider.peek().map(|hc| &hc.cost)
     }
 
     pub fn add_edge(&mut self, parent: K, key: K, additional_cost: C) -> bool {
         let new_cost = self.cost(&parent).unwrap() + &additional_cost;
-        if self.cells.get(&key).is_some() {
-            return false;
+        if let Some(existing_cell) = self.cells.get(&key) {
+            // Over a zero-cost edge the parent may itself be reached from the key, and recording it
+            // would make the parents loop. So with a zero-cost cycle, only the way around it that
+            // was found first is kept.
+            let loops_back = self.all_parents.is_some()
+                && existing_cell.cost == new_cost
+                && self.cost(&parent) == Some(&new_cost)
+                && self.keys_on_paths_to([&parent]).contains(&key);
+            if let Some(all_parents) = self.all_parents.as_mut() {
+                // Roots have no parents, even if they can be reached from other roots
+                if existing_cell.cost == new_cost && existing_cell.parent.is_some() {
+                    let parents = all_parents.get_mut(&key).unwrap();
+                    if !loops_back && !parents.contains(&parent) {
+                        parents.push(parent);
+                    }
+                    return false;
+                }
+            }
+            if existing_cell.settled || existing_cell.cost <= new_cost {
+                return false;
+            }
         }
-        self.consider.push(HeapCell {
-            key: key.clone(),
-            cost: new_cost.clone(),
-        });
-        self.cells.insert(
+        if let Some(all_parents) = self.all_parents.as_mut() {
+            all_parents.insert(key.clone(), vec![parent.clone()]);
+        }
+        self.push(key.clone(), new_cost.clone());
+        let old_cell = self.cells.insert(
             key,
             BfsCell {
                 parent: Some(parent),
                 cost: new_cost,
+                settled: false,
             },
         );
+        if old_cell.is_none() {
+            self.instrument.on_discover();
+        }
         true
     }
 
     pub fn get_path_to(&self, key: &K) -> Option<Vec<K>> {
-        let mut result = Vec::new();
-        let mut currentlly_at = Some(key);
-        while let Some(at) = currentlly_at {
-            result.push(at.clone());
-            currentlly_at = self.cells.get(at)?.parent.as_ref();
-        }
-        result.reverse();
-        Some(result)
+        path_through_parents(&self.cells, key)
     }
 
     pub fn path_to(&self, key: &K) -> Vec<K> {
@@ -154,6 +395,93 @@ where
     pub fn all_known(&self) -> impl Iterator<Item = &K> {
         self.cells.keys()
     }
+
+    // (parent, child) pairs of the search tree
+    pub fn tree_edges(&self) -> impl Iterator<Item = (&K, &K)> {
+        self.cells
+            .iter()
+            .filter_map(|(key, cell)| Some((cell.parent.as_ref()?, key)))
+    }
+
+    pub fn all_parents(&self, key: &K) -> &[K] {
+        self.all_parents
+            .as_ref()
+            .expect("all parents are only tracked when using with_all_parents")
+            .get(key)
+            .map_or(&[], |parents| parents.as_slice())
+    }
+
+    pub fn all_paths_to<'a>(&'a self, key: &'a K) -> impl 'a + Iterator<Item = Vec<K>> {
+        // Each entry is a key and the index of the next parent of it to go up to. Reversing the
+        // keys of the stack gives the path.
+        let mut stack = Vec::<(&K, usize)>::new();
+        if self.cells.contains_key(key) {
+            stack.push((key, 0));
+        }
+        std::iter::from_fn(move || {
+            while let Some((at, next_parent_index)) = stack.last_mut() {
+                let parents = self.all_parents(at);
+                if parents.is_empty() {
+                    let path = stack.iter().rev().map(|(key, _)| (*key).clone()).collect();
+                    stack.pop();
+                    return Some(path);
+                }
+                if let Some(parent) = parents.get(*next_parent_index) {
+                    *next_parent_index += 1;
+                    stack.push((parent, 0));
+                } else {
+                    stack.pop();
+                }
+            }
+            None
+        })
+    }
+
+    pub fn count_paths_to<N: Clone + Zero + One>(&self, key: &K) -> N {
+        if !self.cells.contains_key(key) {
+            return N::zero();
+        }
+        let mut counts = HashMap::<&K, N>::new();
+        // The bool is whether the parents were already pushed, so the count can be summed up
+        let mut stack = vec![(key, false)];
+        while let Some((at, parents_pushed)) = stack.pop() {
+            if counts.contains_key(at) {
+                continue;
+            }
+            let parents = self.all_parents(at);
+            if parents.is_empty() {
+                counts.insert(at, N::one());
+            } else if parents_pushed {
+                let count = parents
+                    .iter()
+                    .fold(N::zero(), |sum, parent| sum + counts[parent].clone());
+                counts.insert(at, count);
+            } else {
+                stack.push((at, true));
+                stack.extend(
+                    parents
+                        .iter()
+                        .filter(|parent| !counts.contains_key(parent))
+                        .map(|parent| (parent, false)),
+                );
+            }
+        }
+        counts.remove(key).unwrap()
+    }
+
+    pub fn keys_on_paths_to<'a>(&'a self, keys: impl IntoIterator<Item = &'a K>) -> HashSet<&'a K> {
+        let mut result = HashSet::new();
+        let mut to_check = keys
+            .into_iter()
+            .filter(|key| self.cells.contains_key(key))
+            .collect::<Vec<_>>();
+        while let Some(key) = to_check.pop() {
+            if result.insert(key) {
+                to_check.extend(self.all_parents(key));
+            }
+        }
+        result
+    }
 }
 
 impl<K, C> Default for HashMapBfs<K, C>
@@ -166,6 +494,773 @@ where
         Self {
             cells: HashMap::new(),
             consider: BinaryHeap::new(),
+            heuristic: None,
+            all_parents: None,
+            instrument: Instrument::new(),
+        }
+    }
+}
+
+// Grows a search from each end until the two meet. `reverse_edges` gives, for a key, the keys that
+// have edges leading into it - for undirected graphs it is the same as `edges`.
+pub fn bidirectional_path<K, C, FI, RI>(
+    from: K,
+    to: K,
+    mut edges: impl FnMut(&K) -> FI,
+    mut reverse_edges: impl FnMut(&K) -> RI,
+) -> Option<(C, Vec<K>)>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    for<'a> &'a C: Add<&'a C, Output = C>,
+    C: PartialOrd<C> + Ord + Clone + Zero,
+    FI: IntoIterator<Item = (K, C)>,
+    RI: IntoIterator<Item = (K, C)>,
+{
+    if from == to {
+        return Some((C::zero(), vec![from]));
+    }
+
+    fn step<K, C>(
+        this: &mut HashMapBfs<K, C>,
+        other: &HashMapBfs<K, C>,
+        edges: impl IntoIterator<Item = (K, C)>,
+        key: K,
+        best: &mut Option<(C, K)>,
+    ) where
+        K: PartialEq + Eq + Hash + Clone,
+        for<'a> &'a C: Add<&'a C, Output = C>,
+        C: PartialOrd<C> + Ord + Clone,
+    {
+        let mut update_best = |this: &HashMapBfs<K, C>, key: &K| {
+            let Some(other_cost) = other.cost(key) else {
+                return;
+            };
+            let cost = this.cost(key).unwrap() + other_cost;
+            if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+                *best = Some((cost, key.clone()));
+            }
+        };
+        update_best(this, &key);
+        for (neighbor, cost) in edges {
+            this.add_edge(key.clone(), neighbor.clone(), cost);
+            update_best(this, &neighbor);
+        }
+    }
+
+    // No heuristic on either side, so `peek_cost` is a lower bound
+    let mut forward = HashMapBfs::default();
+    forward.add_root(from, C::zero());
+    let mut backward = HashMapBfs::default();
+    backward.add_root(to, C::zero());
+    let mut best: Option<(C, K)> = None;
+
+    // If either side runs out, it has settled everything reachable from its end - including the
+    // other end, if it's reachable at all - so the best meeting point is already known.
+    while let (Some(forward_cost), Some(backward_cost)) =
+        (forward.peek_cost(), backward.peek_cost())
+    {
+        if let Some((best_cost, _)) = &best {
+            if *best_cost <= forward_cost + backward_cost {
+                break;
+            }
+        }
+        if forward_cost <= backward_cost {
+            let Some(key) = forward.consider_next() else {
+                break;
+            };
+            step(&mut forward, &backward, edges(&key), key, &mut best);
+        } else {
+            let Some(key) = backward.consider_next() else {
+                break;
+            };
+            step(&mut backward, &forward, reverse_edges(&key), key, &mut best);
+        }
+    }
+
+    let (cost, meeting_point) = best?;
+    let mut path = forward.path_to(&meeting_point);
+    let mut path_from_meeting_point = backward.path_to(&meeting_point);
+    path_from_meeting_point.pop();
+    path.extend(path_from_meeting_point.into_iter().rev());
+    Some((cost, path))
+}
+
+#[derive(Debug)]
+pub struct PathExclusions<K> {
+    pub keys: HashSet<K>,
+    pub edges: HashSet<(K, K)>,
+}
+
+impl<K> Default for PathExclusions<K> {
+    fn default() -> Self {
+        Self {
+            keys: HashSet::new(),
+            edges: HashSet::new(),
+        }
+    }
+}
+
+// Each key in the result is paired with the cost of reaching it from the start of the path.
+fn shortest_path_with_exclusions<K, C, I>(
+    from: &K,
+    to: &K,
+    edges: &mut impl FnMut(&K) -> I,
+    excluded_keys: &HashSet<K>,
+    excluded_edges: &HashSet<(K, K)>,
+) -> Option<Vec<(K, C)>>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    for<'a> &'a C: Add<&'a C, Output = C>,
+    C: PartialOrd<C> + Ord + Clone + Zero,
+    I: IntoIterator<Item = (K, C)>,
+{
+    if excluded_keys.contains(from) || excluded_keys.contains(to) {
+        return None;
+    }
+    let mut bfs = HashMapBfs::default();
+    bfs.add_root(from.clone(), C::zero());
+    while let Some(key) = bfs.consider_next() {
+        if key == *to {
+            return Some(
+                bfs.path_to(&key)
+                    .into_iter()
+                    .map(|key| {
+                        let cost = bfs.cost(&key).unwrap().clone();
+                        (key, cost)
+                    })
+                    .collect(),
+            );
+        }
+        for (neighbor, cost) in edges(&key) {
+            if excluded_keys.contains(&neighbor)
+                || excluded_edges.contains(&(key.clone(), neighbor.clone()))
+            {
+                continue;
+            }
+            bfs.add_edge(key.clone(), neighbor, cost);
+        }
+    }
+    None
+}
+
+// Yen's algorithm. Yields loopless paths from the cheapest up, each with its cost.
+pub fn k_shortest_paths<K, C, I>(
+    from: K,
+    to: K,
+    exclusions: PathExclusions<K>,
+    mut edges: impl FnMut(&K) -> I,
+) -> impl Iterator<Item = (C, Vec<K>)>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    for<'a> &'a C: Add<&'a C, Output = C>,
+    C: PartialOrd<C> + Ord + Clone + Zero,
+    I: IntoIterator<Item = (K, C)>,
+{
+    let mut found = Vec::<Vec<(K, C)>>::new();
+    let mut candidates = BinaryHeap::<HeapCell<Vec<(K, C)>, C>>::new();
+    let mut already_yielded_or_candidate = HashSet::<Vec<K>>::new();
+    let mut push_candidate = move |candidates: &mut BinaryHeap<_>, path: Vec<(K, C)>| {
+        if already_yielded_or_candidate.insert(path.iter().map(|(key, _)| key.clone()).collect()) {
+            let (_, cost) = path.last().unwrap().clone();
+            candidates.push(HeapCell {
+                key: path,
+                cost: cost.clone(),
+                priority: cost,
+            });
+        }
+    };
+    let mut first = true;
+    std::iter::from_fn(move || {
+        if first {
+            first = false;
+            if let Some(path) = shortest_path_with_exclusions(
+                &from,
+                &to,
+                &mut edges,
+                &exclusions.keys,
+                &exclusions.edges,
+            ) {
+                push_candidate(&mut candidates, path);
+            }
+        } else {
+            let last_found = found.last()?;
+            for spur_index in 0..(last_found.len() - 1) {
+                let root_path = &last_found[..=spur_index];
+                let (spur_key, root_cost) = &root_path[spur_index];
+
+                let mut excluded_edges = exclusions.edges.clone();
+                for path in found.iter() {
+                    if spur_index + 1 < path.len()
+                        && path[..=spur_index]
+                            .iter()
+                            .zip(root_path)
+                            .all(|((a, _), (b, _))| a == b)
+                    {
+                        excluded_edges
+                            .insert((path[spur_index].0.clone(), path[spur_index + 1].0.clone()));
+                    }
+                }
+                let mut excluded_keys = exclusions.keys.clone();
+                excluded_keys.extend(root_path[..spur_index].iter().map(|(key, _)| key.clone()));
+
+                let Some(spur_path) = shortest_path_with_exclusions(
+                    spur_key,
+                    &to,
+                    &mut edges,
+                    &excluded_keys,
+                    &excluded_edges,
+                ) else {
+                    continue;
+                };
+                let candidate = root_path[..spur_index]
+                    .iter()
+                    .cloned()
+                    .chain(
+                        spur_path
+                            .into_iter()
+                            .map(|(key, cost)| (key, root_cost + &cost)),
+                    )
+                    .collect();
+                push_candidate(&mut candidates, candidate);
+            }
+        }
+        let path = candidates.pop()?.key;
+        found.push(path.clone());
+        let (_, cost) = path.last().unwrap().clone();
+        Some((cost, path.into_iter().map(|(key, _)| key).collect()))
+    })
+}
+
+// Dial's algorithm - a bucket per cost, starting from the cheapest cost that still has pending
+// keys. Only worth it when edge costs are small integers, because every cost between the cheapest
+// and the most expensive pending key gets a bucket.
+pub struct BucketBfs<K, H = fn(&K) -> usize> {
+    cells: HashMap<K, BfsCell<K, usize>>,
+    // With a heuristic, keys are bucketed by their cost plus the heuristic
+    buckets: VecDeque<Vec<K>>,
+    first_bucket_cost: usize,
+    heuristic: Option<H>,
+    // Edges are almost always added from the last considered key, so we can skip hashing it.
+    last_considered: Option<(K, usize)>,
+    instrument: Instrument,
+}
+
+impl<K, H> BucketBfs<K, H>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    H: Fn(&K) -> usize,
+{
+    // Same requirements as `HashMapBfs::with_heuristic`. A consistent heuristic also keeps keys
+    // from ever being pushed into a bucket that was already emptied.
+    pub fn with_heuristic(heuristic: H) -> Self {
+        Self {
+            cells: HashMap::new(),
+            buckets: VecDeque::new(),
+            first_bucket_cost: 0,
+            heuristic: Some(heuristic),
+            last_considered: None,
+            instrument: Instrument::new(),
+        }
+    }
+
+    pub fn cost(&self, key: &K) -> Option<&usize> {
+        self.cells.get(key).map(|cell| &cell.cost)
+    }
+
+    pub fn stats(&self) -> Option<InstrumentStats> {
+        self.instrument.stats()
+    }
+
+    fn push(&mut self, key: K, cost: usize) {
+        let cost = if let Some(heuristic) = self.heuristic.as_ref() {
+            cost + heuristic(&key)
+        } else {
+            cost
+        };
+        if self.buckets.is_empty() {
+            self.first_bucket_cost = cost;
+        } else if cost < self.first_bucket_cost {
+            // Can only happen with roots
+            for _ in cost..self.first_bucket_cost {
+                self.buckets.push_front(Vec::new());
+            }
+            self.first_bucket_cost = cost;
+        }
+        let index = cost - self.first_bucket_cost;
+        if self.buckets.len() <= index {
+            self.buckets.resize_with(index + 1, Vec::new);
+        }
+        self.buckets[index].push(key);
+        self.instrument.on_push();
+    }
+
+    pub fn add_root(&mut self, key: K, cost: usize) {
+        let old_cell = self.cells.insert(
+            key.clone(),
+            BfsCell {
+                parent: None,
+                cost,
+                settled: false,
+            },
+        );
+        if old_cell.is_none() {
+            self.instrument.on_discover();
+        }
+        self.push(key, cost);
+    }
+
+    pub fn consider_next(&mut self) -> Option<K> {
+        loop {
+            let bucket = self.buckets.front_mut()?;
+            let Some(key) = bucket.pop() else {
+                self.buckets.pop_front();
+                self.first_bucket_cost += 1;
+                continue;
+            };
+            let cell = self.cells.get_mut(&key).unwrap();
+            if cell.settled {
+                self.instrument.on_stale_pop();
+                continue;
+            }
+            cell.settled = true;
+            self.instrument.on_pop();
+            self.last_considered = Some((key.clone(), cell.cost));
+            return Some(key);
+        }
+    }
+
+    pub fn is_settled(&self, key: &K) -> bool {
+        self.cells.get(key).is_some_and(|cell| cell.settled)
+    }
+
+    pub fn add_edge(&mut self, parent: K, key: K, additional_cost: usize) -> bool {
+        let parent_cost = match &self.last_considered {
+            Some((last_key, last_cost)) if *last_key == parent => *last_cost,
+            _ => *self.cost(&parent).unwrap(),
+        };
+        let new_cost = parent_cost + additional_cost;
+        let new_cell = BfsCell {
+            parent: Some(parent),
+            cost: new_cost,
+            settled: false,
+        };
+        match self.cells.entry(key.clone()) {
+            Entry::Occupied(mut entry) => {
+                let existing_cell = entry.get();
+                if existing_cell.settled || existing_cell.cost <= new_cost {
+                    return false;
+                }
+                entry.insert(new_cell);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(new_cell);
+                self.instrument.on_discover();
+            }
+        }
+        self.push(key, new_cost);
+        true
+    }
+
+    pub fn get_path_to(&self, key: &K) -> Option<Vec<K>> {
+        path_through_parents(&self.cells, key)
+    }
+
+    pub fn path_to(&self, key: &K) -> Vec<K> {
+        self.get_path_to(key).expect("No path found")
+    }
+
+    pub fn all_known(&self) -> impl Iterator<Item = &K> {
+        self.cells.keys()
+    }
+}
+
+impl<K> Default for BucketBfs<K> {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::new(),
+            buckets: VecDeque::new(),
+            first_bucket_cost: 0,
+            heuristic: None,
+            last_considered: None,
+            instrument: Instrument::new(),
+        }
+    }
+}
+
+pub trait HasCoord {
+    fn coord(&self) -> [usize; 2];
+}
+
+impl HasCoord for [usize; 2] {
+    fn coord(&self) -> [usize; 2] {
+        *self
+    }
+}
+
+// Admissible whenever every step costs at least 1
+pub fn manhattan_heuristic<K: HasCoord>(target: [usize; 2]) -> impl Fn(&K) -> usize {
+    move |key| {
+        key.coord()
+            .into_iter()
+            .zip(target)
+            .map(|(a, b)| a.abs_diff(b))
+            .sum()
+    }
+}
+
+// Unit-weight BFS that expands each distance layer in parallel. Since all the keys of a layer are
+// found before any of them is expanded, the distances are the same as HashMapBfs would give.
+pub struct ParallelBfs<K> {
+    cells: HashMap<K, BfsCell<K, usize>>,
+}
+
+impl<K> ParallelBfs<K>
+where
+    K: PartialEq + Eq + Hash + Clone + Send + Sync,
+{
+    // Each key gets whichever parent the threads happen to get to first
+    pub fn run<I>(roots: impl IntoIterator<Item = K>, edges: impl Sync + Fn(&K) -> I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+    {
+        Self::run_with(roots, edges, |_, _| false)
+    }
+
+    // When two parents in the same layer reach a key, `replaces_parent(new, old)` decides which
+    // one it keeps
+    fn run_with<I>(
+        roots: impl IntoIterator<Item = K>,
+        edges: impl Sync + Fn(&K) -> I,
+        replaces_parent: impl Sync + Fn(&K, &K) -> bool,
+    ) -> Self
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let mut cells = HashMap::new();
+        let mut layer = Vec::new();
+        for root in roots {
+            let old_cell = cells.insert(
+                root.clone(),
+                BfsCell {
+                    parent: None,
+                    cost: 0,
+                    settled: true,
+                },
+            );
+            if old_cell.is_none() {
+                layer.push(root);
+            }
+        }
+
+        // The keys of the next layer, each with its parent. Sharded so that threads don't all wait
+        // on the same lock.
+        let hash_builder = RandomState::new();
+        let mut shards = std::iter::repeat_with(|| Mutex::new(HashMap::<K, K>::new()))
+            .take(4 * rayon::current_num_threads())
+            .collect::<Vec<_>>();
+
+        let mut cost = 0;
+        while !layer.is_empty() {
+            cost += 1;
+            layer.par_iter().for_each(|parent| {
+                for key in edges(parent) {
+                    // Keys from previous layers - which are not modified while the layer is expanded
+                    if cells.contains_key(&key) {
+                        continue;
+                    }
+                    let shard_index = hash_builder.hash_one(&key) as usize % shards.len();
+                    let mut shard = shards[shard_index].lock().unwrap();
+                    match shard.entry(key) {
+                        Entry::Occupied(mut entry) => {
+                            if replaces_parent(parent, entry.get()) {
+                                entry.insert(parent.clone());
+                            }
+                        }
+                        Entry::Vacant(entry) => {
+                            entry.insert(parent.clone());
+                        }
+                    }
+                }
+            });
+
+            layer.clear();
+            for shard in shards.iter_mut() {
+                for (key, parent) in shard.get_mut().unwrap().drain() {
+                    layer.push(key.clone());
+                    cells.insert(
+                        key,
+                        BfsCell {
+                            parent: Some(parent),
+                            cost,
+                            settled: true,
+                        },
+                    );
+                }
+            }
+        }
+
+        Self { cells }
+    }
+
+    pub fn cost(&self, key: &K) -> Option<&usize> {
+        self.cells.get(key).map(|cell| &cell.cost)
+    }
+
+    pub fn get_path_to(&self, key: &K) -> Option<Vec<K>> {
+        path_through_parents(&self.cells, key)
+    }
+
+    pub fn path_to(&self, key: &K) -> Vec<K> {
+        self.get_path_to(key).expect("No path found")
+    }
+
+    pub fn all_known(&self) -> impl Iterator<Item = &K> {
+        self.cells.keys()
+    }
+}
+
+impl<K> ParallelBfs<K>
+where
+    K: PartialEq + Eq + Hash + Clone + Ord + Send + Sync,
+{
+    // Reproducible parents, at the price of comparing every parent that reaches a key
+    pub fn run_with_smallest_parents<I>(
+        roots: impl IntoIterator<Item = K>,
+        edges: impl Sync + Fn(&K) -> I,
+    ) -> Self
+    where
+        I: IntoIterator<Item = K>,
+    {
+        Self::run_with(roots, edges, |parent, old_parent| parent < old_parent)
+    }
+}
+
+// Shortest paths for costs that may be negative, using Bellman-Ford with a queue (SPFA). The keys
+// whose costs improved in one round are the only ones expanded in the next. Fails with a cycle whose
+// total cost is negative, if one can be reached from the roots.
+pub struct BellmanFord<K, C> {
+    cells: HashMap<K, BfsCell<K, C>>,
+}
+
+impl<K, C> BellmanFord<K, C>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    for<'a> &'a C: Add<&'a C, Output = C>,
+    C: PartialOrd<C> + Ord + Clone,
+{
+    pub fn run<I>(
+        roots: impl IntoIterator<Item = (K, C)>,
+        mut edges: impl FnMut(&K) -> I,
+    ) -> Result<Self, CycleFound<K>>
+    where
+        I: IntoIterator<Item = (K, C)>,
+    {
+        let mut cells = HashMap::<K, BfsCell<K, C>>::new();
+        for (root, cost) in roots {
+            if cells.get(&root).is_none_or(|cell| cost < cell.cost) {
+                cells.insert(
+                    root,
+                    BfsCell {
+                        parent: None,
+                        cost,
+                        settled: true,
+                    },
+                );
+            }
+        }
+        let mut layer = cells.keys().cloned().collect::<Vec<_>>();
+
+        let mut round = 0;
+        while !layer.is_empty() {
+            let mut next_layer = Vec::new();
+            let mut in_next_layer = HashSet::new();
+            for parent in layer {
+                let parent_cost = cells[&parent].cost.clone();
+                for (key, additional_cost) in edges(&parent) {
+                    let cost = &parent_cost + &additional_cost;
+                    if cells.get(&key).is_some_and(|cell| cell.cost <= cost) {
+                        continue;
+                    }
+                    cells.insert(
+                        key.clone(),
+                        BfsCell {
+                            parent: Some(parent.clone()),
+                            cost,
+                            settled: true,
+                        },
+                    );
+                    // Without negative cycles, every cost is final once all the paths with fewer
+                    // edges than there are keys have been tried
+                    if round >= cells.len() {
+                        return Err(Self::find_cycle(&cells, key));
+                    }
+                    if in_next_layer.insert(key.clone()) {
+                        next_layer.push(key);
+                    }
+                }
+            }
+            layer = next_layer;
+            round += 1;
+        }
+
+        Ok(Self { cells })
+    }
+
+    fn find_cycle(cells: &HashMap<K, BfsCell<K, C>>, mut key: K) -> CycleFound<K> {
+        let parent_of = |key: &K| {
+            cells[key]
+                .parent
+                .clone()
+                .expect("Negative cycle not in parent chain")
+        };
+        // Going back as many steps as there are keys is sure to end up inside the cycle
+        for _ in 0..cells.len() {
+            key = parent_of(&key);
+        }
+        let mut cycle = vec![key.clone()];
+        let mut at = parent_of(&key);
+        while at != key {
+            cycle.push(at.clone());
+            at = parent_of(&at);
+        }
+        cycle.reverse();
+        CycleFound { cycle }
+    }
+
+    pub fn cost(&self, key: &K) -> Option<&C> {
+        self.cells.get(key).map(|cell| &cell.cost)
+    }
+
+    pub fn get_path_to(&self, key: &K) -> Option<Vec<K>> {
+        path_through_parents(&self.cells, key)
+    }
+
+    pub fn path_to(&self, key: &K) -> Vec<K> {
+        self.get_path_to(key).expect("No path found")
+    }
+
+    pub fn all_known(&self) -> impl Iterator<Item = &K> {
+        self.cells.keys()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+
+    #[test]
+    fn linear_bfs_decrease_key_matches_hash_map_bfs() {
+        let edges = [(0, 5, 1), (0, 1, 5), (0, 3, 5), (0, 3, 2), (3, 1, 2)];
+        let edges_from = |key: usize| edges.iter().filter(move |(from, _, _)| *from == key);
+
+        let mut linear = LinearBfs::new(6);
+        linear.add_root(0, 0);
+        while let Some(key) = linear.consider_next() {
+            for &(_, to, cost) in edges_from(key) {
+                linear.add_edge(key, to, cost);
+            }
+        }
+
+        let mut hash_map = HashMapBfs::default();
+        hash_map.add_root(0, 0);
+        while let Some(key) = hash_map.consider_next() {
+            for &(_, to, cost) in edges_from(key) {
+                hash_map.add_edge(key, to, cost);
+            }
+        }
+
+        for key in 0..6 {
+            assert_eq!(linear.cost(key), hash_map.cost(&key), "cost of {key}");
+        }
+        assert_eq!(linear.cost(1), Some(&4));
+    }
+
+    fn all_parents_bfs(edges: &[(usize, usize, usize)]) -> HashMapBfs<usize, usize> {
+        let mut bfs = HashMapBfs::default().with_all_parents();
+        bfs.add_root(0, 0);
+        while let Some(key) = bfs.consider_next() {
+            for &(_, to, cost) in edges.iter().filter(|(from, _, _)| *from == key) {
+                bfs.add_edge(key, to, cost);
+            }
+        }
+        bfs
+    }
+
+    #[test]
+    fn all_parents_counts_diamond_paths() {
+        let bfs = all_parents_bfs(&[
+            (0, 1, 1),
+            (0, 2, 1),
+            (1, 3, 1),
+            (2, 3, 1),
+            (3, 4, 1),
+            (3, 5, 1),
+            (4, 6, 1),
+            (5, 6, 1),
+        ]);
+        assert_eq!(bfs.count_paths_to::<usize>(&6), 4);
+        let mut paths = bfs.all_paths_to(&6).collect::<Vec<_>>();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                vec![0, 1, 3, 4, 6],
+                vec![0, 1, 3, 5, 6],
+                vec![0, 2, 3, 4, 6],
+                vec![0, 2, 3, 5, 6],
+            ]
+        );
+    }
+
+    #[test]
+    fn all_parents_do_not_loop_on_zero_cost_cycle() {
+        let bfs = all_parents_bfs(&[(0, 1, 1), (0, 2, 1), (1, 2, 0), (2, 1, 0), (2, 3, 1)]);
+        let count = bfs.count_paths_to::<usize>(&3);
+        let paths = bfs.all_paths_to(&3).collect::<Vec<_>>();
+        assert_eq!(paths.len(), count);
+        assert!(paths.contains(&vec![0, 2, 3]));
+        for path in paths {
+            assert!(path.iter().all_unique(), "{path:?} is not simple");
+        }
+        assert!(!bfs.all_parents(&1).contains(&2) || !bfs.all_parents(&2).contains(&1));
+    }
+
+    #[test]
+    fn parallel_bfs_matches_hash_map_bfs_on_grid() {
+        // A 6x6 grid with a wall across column 2, open only in the bottom row
+        let open = |[row, col]: [usize; 2]| col != 2 || row == 5;
+        let edges = |&[row, col]: &[usize; 2]| {
+            [[0, 1], [2, 1], [1, 0], [1, 2]]
+                .into_iter()
+                .filter_map(move |[dr, dc]| {
+                    Some([(row + dr).checked_sub(1)?, (col + dc).checked_sub(1)?])
+                })
+                .filter(|&[row, col]| row < 6 && col < 6)
+                .filter(move |&coord| open(coord))
+        };
+
+        let mut hash_map = HashMapBfs::default();
+        hash_map.add_root([0, 0], 0);
+        while let Some(key) = hash_map.consider_next() {
+            for neighbor in edges(&key) {
+                hash_map.add_edge(key, neighbor, 1);
+            }
+        }
+
+        let any = ParallelBfs::run([[0, 0]], edges);
+        let smallest = ParallelBfs::run_with_smallest_parents([[0, 0]], edges);
+        for coord in (0..6).cartesian_product(0..6).map(|(row, col)| [row, col]) {
+            assert_eq!(any.cost(&coord), hash_map.cost(&coord), "cost of {coord:?}");
+            assert_eq!(
+                smallest.cost(&coord),
+                hash_map.cost(&coord),
+                "cost of {coord:?}"
+            );
         }
+        assert_eq!(any.cost(&[0, 3]), Some(&13));
+        assert_eq!(any.cost(&[0, 2]), None);
+        assert_eq!(smallest.path_to(&[1, 1]), vec![[0, 0], [0, 1], [1, 1]]);
     }
 }
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// The state after `prefix + i` steps is the same as the state after `prefix + i + length` steps,
+// for any `i`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub prefix: usize,
+    pub length: usize,
+}
+
+impl Cycle {
+    // The smallest number of steps that gives the same state as `steps` steps
+    pub fn reduce(&self, steps: usize) -> usize {
+        if steps < self.prefix {
+            steps
+        } else {
+            self.prefix + (steps - self.prefix) % self.length
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CycleDetection {
+    // Only keeps two states at a time
+    Floyd,
+    // Only keeps two states at a time, and takes fewer steps than Floyd
+    Brent,
+    // Keeps every state until the first repeat, but never steps past it
+    Hashing,
+}
+
+// The step function updates the state in place. The states must eventually repeat, or this never
+// returns.
+pub fn find_cycle<S>(initial: &S, detection: CycleDetection, mut step: impl FnMut(&mut S)) -> Cycle
+where
+    S: Clone + Eq + Hash,
+{
+    match detection {
+        CycleDetection::Floyd => floyd(initial, &mut step),
+        CycleDetection::Brent => brent(initial, &mut step),
+        CycleDetection::Hashing => hashing(initial, usize::MAX, &mut step).0.unwrap(),
+    }
+}
+
+pub fn state_after<S>(
+    initial: S,
+    steps: usize,
+    detection: CycleDetection,
+    mut step: impl FnMut(&mut S),
+) -> S
+where
+    S: Clone + Eq + Hash,
+{
+    let steps_to_do = match detection {
+        CycleDetection::Floyd | CycleDetection::Brent => {
+            find_cycle(&initial, detection, &mut step).reduce(steps)
+        }
+        CycleDetection::Hashing => {
+            let (cycle, mut states) = hashing(&initial, steps, &mut step);
+            let index = match cycle {
+                Some(cycle) => cycle.reduce(steps),
+                None => steps,
+            };
+            return states.swap_remove(index);
+        }
+    };
+    let mut state = initial;
+    for _ in 0..steps_to_do {
+        step(&mut state);
+    }
+    state
+}
+
+fn floyd<S: Clone + Eq>(initial: &S, step: &mut impl FnMut(&mut S)) -> Cycle {
+    let mut tortoise = initial.clone();
+    let mut hare = initial.clone();
+    loop {
+        step(&mut tortoise);
+        step(&mut hare);
+        step(&mut hare);
+        if tortoise == hare {
+            break;
+        }
+    }
+
+    // The hare is now as many steps ahead of the tortoise as there are in the prefix, modulo the
+    // cycle length - so moving both at the same speed makes them meet at the start of the cycle.
+    let mut prefix = 0;
+    tortoise = initial.clone();
+    while tortoise != hare {
+        step(&mut tortoise);
+        step(&mut hare);
+        prefix += 1;
+    }
+
+    let mut length = 1;
+    step(&mut hare);
+    while tortoise != hare {
+        step(&mut hare);
+        length += 1;
+    }
+    Cycle { prefix, length }
+}
+
+fn brent<S: Clone + Eq>(initial: &S, step: &mut impl FnMut(&mut S)) -> Cycle {
+    // The tortoise waits at powers of two for the hare to come back to it
+    let mut power = 1;
+    let mut length = 1;
+    let mut tortoise = initial.clone();
+    let mut hare = initial.clone();
+    step(&mut hare);
+    while tortoise != hare {
+        if power == length {
+            tortoise = hare.clone();
+            power *= 2;
+            length = 0;
+        }
+        step(&mut hare);
+        length += 1;
+    }
+
+    let mut prefix = 0;
+    tortoise = initial.clone();
+    hare = initial.clone();
+    for _ in 0..length {
+        step(&mut hare);
+    }
+    while tortoise != hare {
+        step(&mut tortoise);
+        step(&mut hare);
+        prefix += 1;
+    }
+    Cycle { prefix, length }
+}
+
+// Returns every state up to and including step `limit`, or up to the first repeat - whichever
+// comes first. In the latter case the repeating state itself is not included.
+fn hashing<S: Clone + Eq + Hash>(
+    initial: &S,
+    limit: usize,
+    step: &mut impl FnMut(&mut S),
+) -> (Option<Cycle>, Vec<S>) {
+    let mut seen = HashMap::new();
+    let mut states = Vec::new();
+    let mut state = initial.clone();
+    loop {
+        if let Some(&prefix) = seen.get(&state) {
+            let length = states.len() - prefix;
+            return (Some(Cycle { prefix, length }), states);
+        }
+        seen.insert(state.clone(), states.len());
+        states.push(state.clone());
+        if limit < states.len() {
+            return (None, states);
+        }
+        step(&mut state);
+    }
+}
@@ -2,6 +2,8 @@ use std::collections::{HashSet, VecDeque};
 use std::hash::Hash;
 use std::ops::Add;
 
+use crate::common::instrument::{Instrument, InstrumentStats};
+
 #[derive(Debug)]
 struct StackCell<K, C> {
     key: K,
@@ -9,52 +11,141 @@ struct StackCell<K, C> {
     children: VecDeque<(K, C)>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DfsEvent<K> {
+    // The key was just added to the path, and edges can now be added from it
+    Enter(K),
+    // All the edges from the key were explored. It is still at the end of the path while this
+    // event is handled, and is removed from it at the next call.
+    Exit(K),
+}
+
 #[derive(Debug)]
-pub struct HashMapDfs<K, C> {
+pub struct HashMapDfs<K, C, B = fn(&K, &C, &HashSet<K>) -> C> {
     root: Option<(K, C)>,
     stack: Vec<StackCell<K, C>>,
     visited: HashSet<K>,
+    // When false, keys are only considered visited while they are in the current path - so every
+    // simple path is explored
+    global_visited: bool,
+    exiting: bool,
+    upper_bound: Option<B>,
+    incumbent: Option<C>,
+    instrument: Instrument,
 }
 
 impl<K, C> HashMapDfs<K, C>
 where
     K: PartialEq + Eq + Hash + Clone,
     for<'a> &'a C: Add<&'a C, Output = C>,
-    C: Clone,
+    C: Clone + PartialOrd,
 {
     pub fn new(root: K, root_cost: C) -> Self {
         Self {
             root: Some((root.clone(), root_cost.clone())),
             stack: Vec::new(),
             visited: HashSet::new(),
+            global_visited: false,
+            exiting: false,
+            upper_bound: None,
+            incumbent: None,
+            instrument: Instrument::new(),
         }
     }
+}
+
+impl<K, C, B> HashMapDfs<K, C, B>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    for<'a> &'a C: Add<&'a C, Output = C>,
+    C: Clone + PartialOrd,
+    B: FnMut(&K, &C, &HashSet<K>) -> C,
+{
+    // Branch and bound. Before a key is considered, the bound is called with its cost and the
+    // visited keys (which, unless visiting globally, are the keys in the path to it) and must
+    // return the highest cost any path through it can end with. If that cannot beat the incumbent
+    // the key is skipped, along with everything that would have been reached through it.
+    pub fn with_upper_bound<B2>(self, upper_bound: B2) -> HashMapDfs<K, C, B2>
+    where
+        B2: FnMut(&K, &C, &HashSet<K>) -> C,
+    {
+        HashMapDfs {
+            root: self.root,
+            stack: self.stack,
+            visited: self.visited,
+            global_visited: self.global_visited,
+            exiting: self.exiting,
+            upper_bound: Some(upper_bound),
+            incumbent: self.incumbent,
+            instrument: self.instrument,
+        }
+    }
+
+    // Visit every key only once, like a classic DFS
+    pub fn with_global_visited(mut self) -> Self {
+        self.global_visited = true;
+        self
+    }
+
+    pub fn stats(&self) -> Option<InstrumentStats> {
+        self.instrument.stats()
+    }
 
     pub fn consider_next(&mut self) -> Option<K> {
         loop {
-            if let Some((root, root_cost)) = self.root.take() {
-                self.visited.insert(root.clone());
-                self.stack.push(StackCell {
-                    key: root.clone(),
-                    cost: root_cost,
-                    children: VecDeque::default(),
-                });
-                return Some(root);
-            }
-            let top_cell = self.stack.last_mut()?;
-            if let Some((key, cost)) = top_cell.children.pop_front() {
-                self.visited.insert(key.clone());
-                self.stack.push(StackCell {
-                    key: key.clone(),
-                    cost,
-                    children: VecDeque::default(),
-                });
+            if let DfsEvent::Enter(key) = self.next_event()? {
                 return Some(key);
-            } else {
+            }
+        }
+    }
+
+    pub fn next_event(&mut self) -> Option<DfsEvent<K>> {
+        if self.exiting {
+            self.exiting = false;
+            let top_cell = self.stack.pop().unwrap();
+            if !self.global_visited {
                 self.visited.remove(&top_cell.key);
-                self.stack.pop();
+                self.instrument.on_forget();
             }
         }
+        if let Some((root, root_cost)) = self.root.take() {
+            self.visited.insert(root.clone());
+            self.instrument.on_discover();
+            self.stack.push(StackCell {
+                key: root.clone(),
+                cost: root_cost,
+                children: VecDeque::default(),
+            });
+            return Some(DfsEvent::Enter(root));
+        }
+        let top_cell = self.stack.last_mut()?;
+        while let Some((key, cost)) = top_cell.children.pop_front() {
+            // With global visiting, the key may have been reached through another child since it
+            // was added
+            if self.visited.contains(&key) {
+                self.instrument.on_stale_pop();
+                continue;
+            }
+            if let (Some(upper_bound), Some(incumbent)) =
+                (self.upper_bound.as_mut(), self.incumbent.as_ref())
+            {
+                if upper_bound(&key, &cost, &self.visited) <= *incumbent {
+                    self.instrument.on_prune();
+                    continue;
+                }
+            }
+            self.instrument.on_pop();
+            self.visited.insert(key.clone());
+            self.instrument.on_discover();
+            self.stack.push(StackCell {
+                key: key.clone(),
+                cost,
+                children: VecDeque::default(),
+            });
+            return Some(DfsEvent::Enter(key));
+        }
+        self.exiting = true;
+        Some(DfsEvent::Exit(top_cell.key.clone()))
     }
 
     pub fn add_edge(&mut self, parent: &K, key: K, additional_cost: C) -> bool {
@@ -63,20 +154,43 @@ where
             top_cell.key == *parent,
             "can only add edges to the currently considered node"
         );
+        assert!(!self.exiting, "cannot add edges to a node that was exited");
         if self.visited.contains(&key) {
             return false;
         }
         let new_cost = &top_cell.cost + &additional_cost;
         top_cell.children.push_back((key, new_cost));
+        self.instrument.on_push();
+        true
+    }
+
+    // Returns true if the cost is the new best one
+    pub fn offer_incumbent(&mut self, cost: C) -> bool {
+        if self
+            .incumbent
+            .as_ref()
+            .is_some_and(|incumbent| cost <= *incumbent)
+        {
+            return false;
+        }
+        self.incumbent = Some(cost);
         true
     }
 
+    pub fn incumbent(&self) -> Option<&C> {
+        self.incumbent.as_ref()
+    }
+
     pub fn was_in_current_path(&self, key: &K) -> bool {
         let top_cell = self.stack.last().expect("No path is being inspected");
         if top_cell.key == *key {
             return false;
         }
-        self.visited.contains(key)
+        if self.global_visited {
+            self.stack.iter().any(|stack_cell| stack_cell.key == *key)
+        } else {
+            self.visited.contains(key)
+        }
     }
 
     pub fn cost(&self, key: &K) -> C {
@@ -112,3 +226,164 @@ where
         Some(rev_it.next()?.key.clone())
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DepthLimitedResult<K, C> {
+    // The path starts at the root and ends at the goal
+    Found { cost: C, path: Vec<K> },
+    // No goal within the limit, but there were keys beyond it
+    CutOff,
+    // No goal at all
+    Exhausted,
+}
+
+// Searches simple paths, keeping only the current one in memory. Keys for which `within_limit`
+// returns false are skipped, along with everything beyond them.
+fn bounded_dfs<K, C, I>(
+    root: K,
+    root_cost: C,
+    mut within_limit: impl FnMut(&K, &C, usize) -> bool,
+    mut edges: impl FnMut(&K) -> I,
+    mut is_goal: impl FnMut(&K) -> bool,
+) -> DepthLimitedResult<K, C>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    for<'a> &'a C: Add<&'a C, Output = C>,
+    I: IntoIterator<Item = (K, C)>,
+{
+    let mut in_path = HashSet::new();
+    let mut stack = Vec::<(K, C, I::IntoIter)>::new();
+    let mut cut_off = false;
+    let mut next = Some((root, root_cost));
+    loop {
+        if let Some((key, cost)) = next.take() {
+            if !within_limit(&key, &cost, stack.len()) {
+                cut_off = true;
+            } else if is_goal(&key) {
+                let mut path = stack.into_iter().map(|(key, _, _)| key).collect::<Vec<_>>();
+                path.push(key);
+                return DepthLimitedResult::Found { cost, path };
+            } else {
+                let children = edges(&key).into_iter();
+                in_path.insert(key.clone());
+                stack.push((key, cost, children));
+            }
+        }
+        let Some((_, cost, children)) = stack.last_mut() else {
+            break;
+        };
+        match children.next() {
+            Some((key, additional_cost)) => {
+                if !in_path.contains(&key) {
+                    next = Some((key, &*cost + &additional_cost));
+                }
+            }
+            None => {
+                let (key, _, _) = stack.pop().unwrap();
+                in_path.remove(&key);
+            }
+        }
+    }
+    if cut_off {
+        DepthLimitedResult::CutOff
+    } else {
+        DepthLimitedResult::Exhausted
+    }
+}
+
+// Only follows paths of up to `depth_limit` edges
+pub fn depth_limited_dfs<K, C, I>(
+    root: K,
+    root_cost: C,
+    depth_limit: usize,
+    edges: impl FnMut(&K) -> I,
+    is_goal: impl FnMut(&K) -> bool,
+) -> DepthLimitedResult<K, C>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    for<'a> &'a C: Add<&'a C, Output = C>,
+    I: IntoIterator<Item = (K, C)>,
+{
+    bounded_dfs(
+        root,
+        root_cost,
+        |_, _, depth| depth <= depth_limit,
+        edges,
+        is_goal,
+    )
+}
+
+// Finds the path with the fewest edges, like a BFS, but only keeps the current path in memory
+pub fn iterative_deepening_dfs<K, C, I>(
+    root: K,
+    root_cost: C,
+    mut edges: impl FnMut(&K) -> I,
+    mut is_goal: impl FnMut(&K) -> bool,
+) -> Option<(C, Vec<K>)>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    for<'a> &'a C: Add<&'a C, Output = C>,
+    C: Clone,
+    I: IntoIterator<Item = (K, C)>,
+{
+    for depth_limit in 0.. {
+        match depth_limited_dfs(
+            root.clone(),
+            root_cost.clone(),
+            depth_limit,
+            &mut edges,
+            &mut is_goal,
+        ) {
+            DepthLimitedResult::Found { cost, path } => return Some((cost, path)),
+            DepthLimitedResult::CutOff => {}
+            DepthLimitedResult::Exhausted => break,
+        }
+    }
+    None
+}
+
+// IDA*. Each round only follows paths whose cost plus heuristic is within a threshold, which is
+// raised to the lowest value that was over it until a goal is found. The heuristic must never
+// overestimate the remaining cost.
+pub fn iterative_deepening_a_star<K, C, I>(
+    root: K,
+    root_cost: C,
+    mut heuristic: impl FnMut(&K) -> C,
+    mut edges: impl FnMut(&K) -> I,
+    mut is_goal: impl FnMut(&K) -> bool,
+) -> Option<(C, Vec<K>)>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    for<'a> &'a C: Add<&'a C, Output = C>,
+    C: Clone + Ord,
+    I: IntoIterator<Item = (K, C)>,
+{
+    let mut threshold = &root_cost + &heuristic(&root);
+    loop {
+        let mut next_threshold = None::<C>;
+        let result = bounded_dfs(
+            root.clone(),
+            root_cost.clone(),
+            |key, cost, _| {
+                let estimate = cost + &heuristic(key);
+                if estimate <= threshold {
+                    return true;
+                }
+                if next_threshold
+                    .as_ref()
+                    .is_none_or(|next_threshold| estimate < *next_threshold)
+                {
+                    next_threshold = Some(estimate);
+                }
+                false
+            },
+            &mut edges,
+            &mut is_goal,
+        );
+        match result {
+            DepthLimitedResult::Found { cost, path } => return Some((cost, path)),
+            DepthLimitedResult::CutOff => threshold = next_threshold.unwrap(),
+            DepthLimitedResult::Exhausted => return None,
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+// Union-find over the indices 0..size
+#[derive(Debug, Clone)]
+pub struct LinearDisjointSet {
+    parents: Vec<usize>,
+    ranks: Vec<u8>,
+    sizes: Vec<usize>,
+    component_count: usize,
+}
+
+impl LinearDisjointSet {
+    pub fn new(size: usize) -> Self {
+        Self {
+            parents: (0..size).collect(),
+            ranks: vec![0; size],
+            sizes: vec![1; size],
+            component_count: size,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.parents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty()
+    }
+
+    // Adds a new index, in a component of its own
+    pub fn push(&mut self) -> usize {
+        let index = self.parents.len();
+        self.parents.push(index);
+        self.ranks.push(0);
+        self.sizes.push(1);
+        self.component_count += 1;
+        index
+    }
+
+    pub fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        let mut at = index;
+        while at != root {
+            at = std::mem::replace(&mut self.parents[at], root);
+        }
+        root
+    }
+
+    // Returns false if they were already in the same component
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return false;
+        }
+        if self.ranks[a] < self.ranks[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        if self.ranks[a] == self.ranks[b] {
+            self.ranks[a] += 1;
+        }
+        self.parents[b] = a;
+        self.sizes[a] += self.sizes[b];
+        self.component_count -= 1;
+        true
+    }
+
+    pub fn same(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    pub fn size_of(&mut self, index: usize) -> usize {
+        let root = self.find(index);
+        self.sizes[root]
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.component_count
+    }
+
+    // Each component lists its indices in increasing order
+    pub fn components(&mut self) -> Vec<Vec<usize>> {
+        let mut component_of_root = HashMap::new();
+        let mut result = Vec::<Vec<usize>>::new();
+        for index in 0..self.len() {
+            let root = self.find(index);
+            let component = *component_of_root.entry(root).or_insert_with(|| {
+                result.push(Vec::with_capacity(self.sizes[root]));
+                result.len() - 1
+            });
+            result[component].push(index);
+        }
+        result
+    }
+}
+
+// Union-find over arbitrary keys, which are added the first time they are seen
+#[derive(Debug, Clone)]
+pub struct DisjointSet<K> {
+    indices: HashMap<K, usize>,
+    keys: Vec<K>,
+    inner: LinearDisjointSet,
+}
+
+impl<K> DisjointSet<K>
+where
+    K: PartialEq + Eq + Hash + Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            indices: HashMap::new(),
+            keys: Vec::new(),
+            inner: LinearDisjointSet::new(0),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    fn index_of(&mut self, key: K) -> usize {
+        if let Some(&index) = self.indices.get(&key) {
+            return index;
+        }
+        let index = self.inner.push();
+        self.keys.push(key.clone());
+        self.indices.insert(key, index);
+        index
+    }
+
+    // Returns false if the key was already known
+    pub fn insert(&mut self, key: K) -> bool {
+        let len = self.len();
+        self.index_of(key);
+        len < self.len()
+    }
+
+    // The representative of the key's component
+    pub fn find(&mut self, key: &K) -> Option<&K> {
+        let index = *self.indices.get(key)?;
+        Some(&self.keys[self.inner.find(index)])
+    }
+
+    // Returns false if they were already in the same component
+    pub fn union(&mut self, a: K, b: K) -> bool {
+        let a = self.index_of(a);
+        let b = self.index_of(b);
+        self.inner.union(a, b)
+    }
+
+    pub fn same(&mut self, a: &K, b: &K) -> bool {
+        match (self.indices.get(a), self.indices.get(b)) {
+            (Some(&a), Some(&b)) => self.inner.same(a, b),
+            _ => false,
+        }
+    }
+
+    pub fn size_of(&mut self, key: &K) -> Option<usize> {
+        let index = *self.indices.get(key)?;
+        Some(self.inner.size_of(index))
+    }
+
+    pub fn component_count(&self) -> usize {
+        self.inner.component_count()
+    }
+
+    // Each component lists its keys in the order they were added
+    pub fn components(&mut self) -> Vec<Vec<K>> {
+        self.inner
+            .components()
+            .into_iter()
+            .map(|component| {
+                component
+                    .into_iter()
+                    .map(|index| self.keys[index].clone())
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl<K> Default for DisjointSet<K>
+where
+    K: PartialEq + Eq + Hash + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
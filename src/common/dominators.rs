@@ -0,0 +1,140 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::common::graph::Neighbors;
+
+// A node dominates another if every path from the root to the other node goes through it. Only
+// nodes reachable from the root are part of the tree.
+#[derive(Debug, Clone)]
+pub struct DominatorTree<K> {
+    keys: Vec<K>,
+    indices: HashMap<K, usize>,
+    // Indexed like `keys`. The root is its own immediate dominator.
+    immediate_dominators: Vec<usize>,
+    subtree_sizes: Vec<usize>,
+}
+
+impl<K> DominatorTree<K>
+where
+    K: PartialEq + Eq + Hash + Clone,
+{
+    // Cooper, Harvey and Kennedy's iterative algorithm. Nodes are indexed in postorder, so that
+    // the root gets the highest index and dominators always have higher indices than the nodes
+    // they dominate.
+    pub fn new<E>(graph: &HashMap<K, E>, root: K) -> Self
+    where
+        E: Neighbors<K>,
+    {
+        let edges_of = |key: &K| -> Vec<K> {
+            graph
+                .get(key)
+                .map(|edges| edges.neighbors().cloned().collect())
+                .unwrap_or_default()
+        };
+
+        let mut keys = Vec::new();
+        let mut indices = HashMap::new();
+        let mut discovered = HashSet::from([root.clone()]);
+        let mut call_stack = vec![(root.clone(), edges_of(&root))];
+        while let Some((key, edges)) = call_stack.last_mut() {
+            if let Some(neighbor) = edges.pop() {
+                if discovered.insert(neighbor.clone()) {
+                    let neighbor_edges = edges_of(&neighbor);
+                    call_stack.push((neighbor, neighbor_edges));
+                }
+                continue;
+            }
+            indices.insert(key.clone(), keys.len());
+            keys.push(key.clone());
+            call_stack.pop();
+        }
+
+        let mut predecessors = vec![Vec::new(); keys.len()];
+        for (index, key) in keys.iter().enumerate() {
+            for neighbor in edges_of(key) {
+                predecessors[indices[&neighbor]].push(index);
+            }
+        }
+
+        let root_index = keys.len() - 1;
+        let mut immediate_dominators = vec![None; keys.len()];
+        immediate_dominators[root_index] = Some(root_index);
+        let intersect = |immediate_dominators: &[Option<usize>], mut a: usize, mut b: usize| {
+            while a != b {
+                while a < b {
+                    a = immediate_dominators[a].unwrap();
+                }
+                while b < a {
+                    b = immediate_dominators[b].unwrap();
+                }
+            }
+            a
+        };
+        let mut changed = true;
+        while changed {
+            changed = false;
+            // Reverse postorder
+            for index in (0..root_index).rev() {
+                let new_immediate_dominator = predecessors[index]
+                    .iter()
+                    .copied()
+                    .filter(|&predecessor| immediate_dominators[predecessor].is_some())
+                    .reduce(|a, b| intersect(&immediate_dominators, a, b));
+                if new_immediate_dominator != immediate_dominators[index] {
+                    immediate_dominators[index] = new_immediate_dominator;
+                    changed = true;
+                }
+            }
+        }
+        let immediate_dominators = immediate_dominators
+            .into_iter()
+            .map(|immediate_dominator| immediate_dominator.unwrap())
+            .collect::<Vec<_>>();
+
+        let mut subtree_sizes = vec![1; keys.len()];
+        for index in 0..root_index {
+            subtree_sizes[immediate_dominators[index]] += subtree_sizes[index];
+        }
+
+        Self {
+            keys,
+            indices,
+            immediate_dominators,
+            subtree_sizes,
+        }
+    }
+
+    pub fn root(&self) -> &K {
+        self.keys.last().unwrap()
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    // None for the root and for nodes that are not reachable from it
+    pub fn immediate_dominator(&self, key: &K) -> Option<&K> {
+        let index = *self.indices.get(key)?;
+        let immediate_dominator = self.immediate_dominators[index];
+        (immediate_dominator != index).then(|| &self.keys[immediate_dominator])
+    }
+
+    // Every node dominates itself
+    pub fn dominates(&self, dominator: &K, key: &K) -> bool {
+        let (Some(&dominator), Some(&(mut index))) =
+            (self.indices.get(dominator), self.indices.get(key))
+        else {
+            return false;
+        };
+        // Dominators always have higher indices, so there is no need to go past `dominator`
+        while index < dominator {
+            index = self.immediate_dominators[index];
+        }
+        index == dominator
+    }
+
+    // The number of nodes the key dominates, including itself
+    pub fn subtree_size(&self, key: &K) -> Option<usize> {
+        Some(self.subtree_sizes[*self.indices.get(key)?])
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Debug, Write};
+use std::hash::Hash;
+use std::ops::Add;
+
+use crate::common::bfs::HashMapBfs;
+
+type NodeLabel<'a, K> = Box<dyn 'a + Fn(&K) -> String>;
+type EdgeLabel<'a, K, C> = Box<dyn 'a + Fn(&K, &K, &C) -> String>;
+type Cluster<'a, K> = Box<dyn 'a + Fn(&K) -> Option<String>>;
+
+// Writes graphs in Graphviz's DOT format. Nodes are labeled with their Debug representation unless
+// a node label callback is given, and edges are unlabeled unless an edge label callback is given.
+pub struct Dot<'a, K, C> {
+    directed: bool,
+    node_label: Option<NodeLabel<'a, K>>,
+    edge_label: Option<EdgeLabel<'a, K, C>>,
+    cluster: Option<Cluster<'a, K>>,
+    highlighted_nodes: HashSet<K>,
+    highlighted_edges: HashSet<(K, K)>,
+}
+
+// HashMaps iterate in a different order on every run, so the graphs taken from them are sorted
+// to keep the output the same
+fn sorted_by_debug<T>(items: impl Iterator<Item = T>, key: impl Fn(&T) -> String) -> Vec<T> {
+    let mut items = items.collect::<Vec<_>>();
+    items.sort_by_cached_key(key);
+    items
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+impl<'a, K, C> Dot<'a, K, C>
+where
+    K: PartialEq + Eq + Hash + Clone + Debug,
+{
+    pub fn new(directed: bool) -> Self {
+        Self {
+            directed,
+            node_label: None,
+            edge_label: None,
+            cluster: None,
+            highlighted_nodes: HashSet::new(),
+            highlighted_edges: HashSet::new(),
+        }
+    }
+
+    pub fn node_label(mut self, node_label: impl 'a + Fn(&K) -> String) -> Self {
+        self.node_label = Some(Box::new(node_label));
+        self
+    }
+
+    pub fn edge_label(mut self, edge_label: impl 'a + Fn(&K, &K, &C) -> String) -> Self {
+        self.edge_label = Some(Box::new(edge_label));
+        self
+    }
+
+    // Nodes for which the callback returns the same name are drawn inside a box with that name
+    pub fn cluster(mut self, cluster: impl 'a + Fn(&K) -> Option<String>) -> Self {
+        self.cluster = Some(Box::new(cluster));
+        self
+    }
+
+    pub fn highlight_path(mut self, path: &[K]) -> Self {
+        self.highlighted_nodes.extend(path.iter().cloned());
+        for pair in path.windows(2) {
+            self.highlighted_edges
+                .insert((pair[0].clone(), pair[1].clone()));
+            if !self.directed {
+                self.highlighted_edges
+                    .insert((pair[1].clone(), pair[0].clone()));
+            }
+        }
+        self
+    }
+
+    // Nodes are numbered in the order they are given
+    pub fn write<'b>(
+        &self,
+        out: &mut impl Write,
+        nodes: impl IntoIterator<Item = &'b K>,
+        edges: impl IntoIterator<Item = (&'b K, &'b K, &'b C)>,
+    ) -> std::fmt::Result
+    where
+        K: 'b,
+        C: 'b,
+    {
+        let (graph_type, edge_op) = if self.directed {
+            ("digraph", "->")
+        } else {
+            ("graph", "--")
+        };
+        writeln!(out, "{graph_type} {{")?;
+
+        let mut node_ids = HashMap::<&K, usize>::new();
+        // Keyed by name so the clusters are numbered the same way on every run
+        let mut clusters = BTreeMap::<String, Vec<usize>>::new();
+        for node in nodes {
+            if node_ids.contains_key(node) {
+                continue;
+            }
+            let id = node_ids.len();
+            node_ids.insert(node, id);
+            let label = if let Some(node_label) = self.node_label.as_ref() {
+                node_label(node)
+            } else {
+                format!("{node:?}")
+            };
+            write!(out, "    n{id} [label=\"{}\"", escape(&label))?;
+            if self.highlighted_nodes.contains(node) {
+                write!(out, ", color=red, penwidth=2")?;
+            }
+            writeln!(out, "];")?;
+            if let Some(cluster) = self.cluster.as_ref().and_then(|cluster| cluster(node)) {
+                clusters.entry(cluster).or_default().push(id);
+            }
+        }
+
+        for (i, (name, ids)) in clusters.into_iter().enumerate() {
+            writeln!(out, "    subgraph cluster_{i} {{")?;
+            writeln!(out, "        label=\"{}\";", escape(&name))?;
+            for id in ids {
+                writeln!(out, "        n{id};")?;
+            }
+            writeln!(out, "    }}")?;
+        }
+
+        let mut written_edges = HashSet::<(&K, &K)>::new();
+        for (from, to, data) in edges {
+            // Undirected graphs are usually given with each edge in both directions
+            if !self.directed && written_edges.contains(&(to, from)) {
+                continue;
+            }
+            if !written_edges.insert((from, to)) {
+                continue;
+            }
+            let (Some(from_id), Some(to_id)) = (node_ids.get(from), node_ids.get(to)) else {
+                panic!("Edge {from:?} {edge_op} {to:?} uses a node that was not given");
+            };
+            let mut attributes = Vec::new();
+            if let Some(edge_label) = self.edge_label.as_ref() {
+                attributes.push(format!("label=\"{}\"", escape(&edge_label(from, to, data))));
+            }
+            if self.highlighted_edges.contains(&(from.clone(), to.clone())) {
+                attributes.push("color=red, penwidth=2".to_string());
+            }
+            if attributes.is_empty() {
+                writeln!(out, "    n{from_id} {edge_op} n{to_id};")?;
+            } else {
+                writeln!(
+                    out,
+                    "    n{from_id} {edge_op} n{to_id} [{}];",
+                    attributes.join(", ")
+                )?;
+            }
+        }
+
+        writeln!(out, "}}")
+    }
+
+    pub fn adjacency_map(&self, graph: &HashMap<K, HashMap<K, C>>) -> String {
+        let nodes = sorted_by_debug(
+            graph
+                .iter()
+                .flat_map(|(node, edges)| std::iter::once(node).chain(edges.keys())),
+            |node| format!("{node:?}"),
+        );
+        let edges = sorted_by_debug(
+            graph
+                .iter()
+                .flat_map(|(from, edges)| edges.iter().map(move |(to, data)| (from, to, data))),
+            |(from, to, _)| format!("{from:?} {to:?}"),
+        );
+        let mut result = String::new();
+        self.write(&mut result, nodes, edges).unwrap();
+        result
+    }
+
+    // The edges are given the cost of the child they lead to
+    pub fn bfs_tree<H>(&self, bfs: &HashMapBfs<K, C, H>) -> String
+    where
+        for<'c> &'c C: Add<&'c C, Output = C>,
+        C: PartialOrd<C> + Ord + Clone,
+        H: Fn(&K) -> C,
+    {
+        let nodes = sorted_by_debug(bfs.all_known(), |node| format!("{node:?}"));
+        let edges = sorted_by_debug(
+            bfs.tree_edges()
+                .map(|(parent, child)| (parent, child, bfs.cost(child).unwrap())),
+            |(parent, child, _)| format!("{parent:?} {child:?}"),
+        );
+        let mut result = String::new();
+        self.write(&mut result, nodes, edges).unwrap();
+        result
+    }
+}
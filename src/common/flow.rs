@@ -0,0 +1,352 @@
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::ops::{Add, Sub};
+
+use num::Zero;
+
+#[derive(Debug, Clone)]
+pub struct MinCut<K, C> {
+    pub value: C,
+    pub cut_edges: Vec<(K, K)>,
+    // For an s-t cut, the first side is the one with the source
+    pub sides: [Vec<K>; 2],
+}
+
+#[derive(Debug, Clone)]
+struct FlowEdge<C> {
+    to: usize,
+    capacity: C,
+    residual: C,
+}
+
+// Each edge is stored right next to its reverse, so the reverse of edge `i` is edge `i ^ 1`
+#[derive(Debug, Clone)]
+pub struct FlowNetwork<K, C> {
+    indices: HashMap<K, usize>,
+    keys: Vec<K>,
+    edges: Vec<FlowEdge<C>>,
+    adjacency: Vec<Vec<usize>>,
+}
+
+impl<K, C> FlowNetwork<K, C>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    C: Copy + Ord + Zero + Add<Output = C> + Sub<Output = C>,
+{
+    pub fn new() -> Self {
+        Self {
+            indices: HashMap::new(),
+            keys: Vec::new(),
+            edges: Vec::new(),
+            adjacency: Vec::new(),
+        }
+    }
+
+    // Treats every entry as a directed edge. For an undirected graph, where each edge appears in
+    // both directions, this gives each direction the full capacity - which is the same thing.
+    pub fn from_adjacency(graph: &HashMap<K, HashMap<K, C>>) -> Self {
+        let mut network = Self::new();
+        for (from, edges) in graph {
+            for (to, capacity) in edges {
+                network.add_edge(from.clone(), to.clone(), *capacity);
+            }
+        }
+        network
+    }
+
+    fn index_of(&mut self, key: K) -> usize {
+        let next_index = self.keys.len();
+        *self.indices.entry(key.clone()).or_insert_with(|| {
+            self.keys.push(key);
+            self.adjacency.push(Vec::new());
+            next_index
+        })
+    }
+
+    fn push_edge(&mut self, from: usize, to: usize, capacity: C, reverse_capacity: C) {
+        self.adjacency[from].push(self.edges.len());
+        self.edges.push(FlowEdge {
+            to,
+            capacity,
+            residual: capacity,
+        });
+        self.adjacency[to].push(self.edges.len());
+        self.edges.push(FlowEdge {
+            to: from,
+            capacity: reverse_capacity,
+            residual: reverse_capacity,
+        });
+    }
+
+    pub fn add_edge(&mut self, from: K, to: K, capacity: C) {
+        let from = self.index_of(from);
+        let to = self.index_of(to);
+        self.push_edge(from, to, capacity, C::zero());
+    }
+
+    pub fn add_undirected_edge(&mut self, a: K, b: K, capacity: C) {
+        let a = self.index_of(a);
+        let b = self.index_of(b);
+        self.push_edge(a, b, capacity, capacity);
+    }
+
+    fn levels_from(&self, source: usize) -> Vec<Option<usize>> {
+        let mut levels = vec![None; self.keys.len()];
+        levels[source] = Some(0);
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            for &edge in self.adjacency[node].iter() {
+                let FlowEdge { to, residual, .. } = self.edges[edge];
+                if levels[to].is_none() && C::zero() < residual {
+                    levels[to] = Some(levels[node].unwrap() + 1);
+                    queue.push_back(to);
+                }
+            }
+        }
+        levels
+    }
+
+    fn augment(
+        &mut self,
+        node: usize,
+        sink: usize,
+        limit: C,
+        levels: &[Option<usize>],
+        next_edge: &mut [usize],
+    ) -> C {
+        if node == sink {
+            return limit;
+        }
+        while next_edge[node] < self.adjacency[node].len() {
+            let edge = self.adjacency[node][next_edge[node]];
+            let FlowEdge { to, residual, .. } = self.edges[edge];
+            if C::zero() < residual && levels[to] == levels[node].map(|level| level + 1) {
+                let pushed = self.augment(to, sink, limit.min(residual), levels, next_edge);
+                if C::zero() < pushed {
+                    self.edges[edge].residual = self.edges[edge].residual - pushed;
+                    self.edges[edge ^ 1].residual = self.edges[edge ^ 1].residual + pushed;
+                    return pushed;
+                }
+            }
+            next_edge[node] += 1;
+        }
+        C::zero()
+    }
+
+    // Dinic's algorithm. The flow stays in the network, so calling this again with the same source
+    // and sink gives zero. So does a source that is also the sink, or a key that has no edges.
+    pub fn max_flow(&mut self, source: &K, sink: &K) -> C {
+        let Some((&source, &sink)) = self.indices.get(source).zip(self.indices.get(sink)) else {
+            return C::zero();
+        };
+        if source == sink {
+            return C::zero();
+        }
+        let mut total = C::zero();
+        loop {
+            let levels = self.levels_from(source);
+            if levels[sink].is_none() {
+                return total;
+            }
+            let mut next_edge = vec![0; self.keys.len()];
+            // Nothing can be pushed along a single edge more than its capacity, so the sum of all
+            // capacities out of the source is a good enough "unlimited"
+            let limit = self.adjacency[source]
+                .iter()
+                .fold(C::zero(), |sum, &edge| sum + self.edges[edge].residual);
+            loop {
+                let pushed = self.augment(source, sink, limit, &levels, &mut next_edge);
+                if pushed == C::zero() {
+                    break;
+                }
+                total = total + pushed;
+            }
+        }
+    }
+
+    pub fn flow(&self, from: &K, to: &K) -> C {
+        let Some((&from, &to)) = self.indices.get(from).zip(self.indices.get(to)) else {
+            return C::zero();
+        };
+        self.adjacency[from]
+            .iter()
+            .map(|&edge| &self.edges[edge])
+            .filter(|edge| edge.to == to && edge.residual < edge.capacity)
+            .fold(C::zero(), |sum, edge| sum + edge.capacity - edge.residual)
+    }
+
+    // None if there is nothing to cut - the source is the sink, or one of them has no edges
+    pub fn min_cut(&mut self, source: &K, sink: &K) -> Option<MinCut<K, C>> {
+        if source == sink || !self.indices.contains_key(source) || !self.indices.contains_key(sink)
+        {
+            return None;
+        }
+        let value = self.max_flow(source, sink);
+        let levels = self.levels_from(self.indices[source]);
+        let cut_edges = (0..self.keys.len())
+            .filter(|&node| levels[node].is_some())
+            .flat_map(|node| self.adjacency[node].iter().map(move |&edge| (node, edge)))
+            .filter(|&(_, edge)| {
+                let FlowEdge { to, capacity, .. } = self.edges[edge];
+                levels[to].is_none() && C::zero() < capacity
+            })
+            .map(|(node, edge)| {
+                (
+                    self.keys[node].clone(),
+                    self.keys[self.edges[edge].to].clone(),
+                )
+            })
+            .collect();
+        let (source_side, sink_side) = self
+            .keys
+            .iter()
+            .cloned()
+            .zip(levels)
+            .partition::<Vec<_>, _>(|(_, level)| level.is_some());
+        Some(MinCut {
+            value,
+            cut_edges,
+            sides: [source_side, sink_side]
+                .map(|side| side.into_iter().map(|(key, _)| key).collect()),
+        })
+    }
+}
+
+impl<K, C> Default for FlowNetwork<K, C>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    C: Copy + Ord + Zero + Add<Output = C> + Sub<Output = C>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Stoer-Wagner. The graph must be undirected - every edge must appear in both directions with the
+// same weight. Returns None if there are fewer than two nodes to separate.
+pub fn global_min_cut<K, C>(graph: &HashMap<K, HashMap<K, C>>) -> Option<MinCut<K, C>>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    C: Copy + Ord + Zero + Add<Output = C>,
+{
+    let keys = graph.keys().cloned().collect::<Vec<_>>();
+    let indices = keys
+        .iter()
+        .enumerate()
+        .map(|(index, key)| (key, index))
+        .collect::<HashMap<_, _>>();
+    let mut adjacency = graph
+        .values()
+        .map(|edges| {
+            edges
+                .iter()
+                .map(|(to, weight)| (indices[to], *weight))
+                .collect::<HashMap<usize, C>>()
+        })
+        .collect::<Vec<_>>();
+    // The original nodes each remaining node stands for
+    let mut merged = (0..keys.len()).map(|index| vec![index]).collect::<Vec<_>>();
+    let mut remaining = (0..keys.len()).collect::<Vec<_>>();
+
+    let mut best: Option<(C, Vec<usize>)> = None;
+    while 1 < remaining.len() {
+        // Add nodes one by one, always the one most tightly connected to those already added
+        let mut connectivity = vec![C::zero(); keys.len()];
+        let mut added = vec![false; keys.len()];
+        let mut heap = remaining
+            .iter()
+            .map(|&node| (C::zero(), node))
+            .collect::<BinaryHeap<_>>();
+        let mut order = Vec::with_capacity(remaining.len());
+        while let Some((weight, node)) = heap.pop() {
+            if added[node] || weight != connectivity[node] {
+                continue;
+            }
+            added[node] = true;
+            order.push(node);
+            for (&neighbor, &edge_weight) in adjacency[node].iter() {
+                if !added[neighbor] {
+                    connectivity[neighbor] = connectivity[neighbor] + edge_weight;
+                    heap.push((connectivity[neighbor], neighbor));
+                }
+            }
+        }
+        let &[.., s, t] = order.as_slice() else {
+            unreachable!()
+        };
+
+        // Cutting off the last node is the best cut between the last two nodes
+        let cut_of_phase = connectivity[t];
+        if best
+            .as_ref()
+            .is_none_or(|(best_value, _)| cut_of_phase < *best_value)
+        {
+            best = Some((cut_of_phase, merged[t].clone()));
+        }
+
+        let t_edges = std::mem::take(&mut adjacency[t]);
+        for (neighbor, weight) in t_edges {
+            adjacency[neighbor].remove(&t);
+            if neighbor != s {
+                let entry = adjacency[s].entry(neighbor).or_insert_with(C::zero);
+                *entry = *entry + weight;
+                let entry = adjacency[neighbor].entry(s).or_insert_with(C::zero);
+                *entry = *entry + weight;
+            }
+        }
+        let t_merged = std::mem::take(&mut merged[t]);
+        merged[s].extend(t_merged);
+        remaining.retain(|&node| node != t);
+    }
+
+    let (value, cut_off) = best?;
+    let cut_off = cut_off.into_iter().collect::<HashSet<_>>();
+    let cut_edges = graph
+        .iter()
+        .filter(|(from, _)| cut_off.contains(&indices[from]))
+        .flat_map(|(from, edges)| edges.keys().map(move |to| (from, to)))
+        .filter(|(_, to)| !cut_off.contains(&indices[to]))
+        .map(|(from, to)| (from.clone(), to.clone()))
+        .collect();
+    let (inside, outside) = keys
+        .into_iter()
+        .enumerate()
+        .partition::<Vec<_>, _>(|(index, _)| cut_off.contains(index));
+    Some(MinCut {
+        value,
+        cut_edges,
+        sides: [inside, outside].map(|side| side.into_iter().map(|(_, key)| key).collect()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn network() -> FlowNetwork<char, u32> {
+        let mut network = FlowNetwork::new();
+        network.add_edge('s', 'a', 3);
+        network.add_edge('a', 't', 2);
+        network
+    }
+
+    #[test]
+    fn max_flow_from_a_key_to_itself_is_zero() {
+        let mut network = network();
+        assert_eq!(network.max_flow(&'a', &'a'), 0);
+        assert!(network.min_cut(&'a', &'a').is_none());
+        assert_eq!(network.max_flow(&'s', &'t'), 2);
+    }
+
+    #[test]
+    fn max_flow_with_unknown_keys_is_zero() {
+        let mut network = network();
+        assert_eq!(network.max_flow(&'x', &'t'), 0);
+        assert_eq!(network.max_flow(&'s', &'x'), 0);
+        assert!(network.min_cut(&'x', &'t').is_none());
+        assert!(network.min_cut(&'s', &'x').is_none());
+        let cut = network.min_cut(&'s', &'t').unwrap();
+        assert_eq!(cut.value, 2);
+        assert_eq!(cut.cut_edges, vec![('a', 't')]);
+    }
+}
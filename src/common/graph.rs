@@ -0,0 +1,269 @@
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use crate::common::disjoint_set::DisjointSet;
+
+// The edges out of a single node, in any of the shapes our adjacency maps use
+pub trait Neighbors<K> {
+    fn neighbors<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where
+        K: 'a;
+}
+
+impl<K> Neighbors<K> for Vec<K> {
+    fn neighbors<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where
+        K: 'a,
+    {
+        self.iter()
+    }
+}
+
+impl<K> Neighbors<K> for HashSet<K> {
+    fn neighbors<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where
+        K: 'a,
+    {
+        self.iter()
+    }
+}
+
+impl<K, C> Neighbors<K> for HashMap<K, C> {
+    fn neighbors<'a>(&'a self) -> impl Iterator<Item = &'a K>
+    where
+        K: 'a,
+    {
+        self.keys()
+    }
+}
+
+// Edge directions are ignored. Nodes that only appear as neighbors are included too.
+pub fn connected_components<K, E>(graph: &HashMap<K, E>) -> Vec<Vec<K>>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    E: Neighbors<K>,
+{
+    let mut disjoint_set = DisjointSet::new();
+    for (node, edges) in graph {
+        disjoint_set.insert(node.clone());
+        for neighbor in edges.neighbors() {
+            disjoint_set.union(node.clone(), neighbor.clone());
+        }
+    }
+    disjoint_set.components()
+}
+
+// Gives every node, including the ones that only appear as neighbors, a dense index
+fn index_graph<'a, K, E>(graph: &'a HashMap<K, E>) -> (Vec<K>, Vec<Vec<usize>>)
+where
+    K: PartialEq + Eq + Hash + Clone,
+    E: Neighbors<K>,
+{
+    let mut indices = HashMap::<&'a K, usize>::new();
+    let mut keys = Vec::new();
+    let mut index_of = |key: &'a K, keys: &mut Vec<K>| {
+        *indices.entry(key).or_insert_with(|| {
+            keys.push(key.clone());
+            keys.len() - 1
+        })
+    };
+    let mut adjacency = Vec::<Vec<usize>>::new();
+    for (node, edges) in graph {
+        let node = index_of(node, &mut keys);
+        let edges = edges
+            .neighbors()
+            .map(|neighbor| index_of(neighbor, &mut keys))
+            .collect::<Vec<_>>();
+        adjacency.resize_with(keys.len(), Vec::new);
+        adjacency[node] = edges;
+    }
+    (keys, adjacency)
+}
+
+// Tarjan's algorithm, without recursion. The components come in reverse topological order - no
+// edge leads from a component to one that comes after it.
+fn tarjan(adjacency: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut order = vec![None; adjacency.len()];
+    let mut low_link = vec![0; adjacency.len()];
+    let mut on_stack = vec![false; adjacency.len()];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut next_order = 0;
+
+    for root in 0..adjacency.len() {
+        if order[root].is_some() {
+            continue;
+        }
+        // Each frame is a node and the position of the next edge to check
+        let mut call_stack = vec![(root, 0)];
+        order[root] = Some(next_order);
+        low_link[root] = next_order;
+        next_order += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some((node, edge_position)) = call_stack.last_mut() {
+            let node = *node;
+            if let Some(&neighbor) = adjacency[node].get(*edge_position) {
+                *edge_position += 1;
+                match order[neighbor] {
+                    None => {
+                        order[neighbor] = Some(next_order);
+                        low_link[neighbor] = next_order;
+                        next_order += 1;
+                        stack.push(neighbor);
+                        on_stack[neighbor] = true;
+                        call_stack.push((neighbor, 0));
+                    }
+                    Some(neighbor_order) => {
+                        if on_stack[neighbor] {
+                            low_link[node] = low_link[node].min(neighbor_order);
+                        }
+                    }
+                }
+                continue;
+            }
+
+            call_stack.pop();
+            if let Some(&(parent, _)) = call_stack.last() {
+                low_link[parent] = low_link[parent].min(low_link[node]);
+            }
+            if Some(low_link[node]) == order[node] {
+                let mut component = Vec::new();
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+// The components come in topological order - every edge between components leads to a later one
+pub fn strongly_connected_components<K, E>(graph: &HashMap<K, E>) -> Vec<Vec<K>>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    E: Neighbors<K>,
+{
+    let (keys, adjacency) = index_graph(graph);
+    tarjan(&adjacency)
+        .into_iter()
+        .rev()
+        .map(|component| {
+            component
+                .into_iter()
+                .map(|index| keys[index].clone())
+                .collect()
+        })
+        .collect()
+}
+
+// The DAG of strongly connected components. Components are in topological order, so every edge
+// leads from a component to one with a higher index.
+#[derive(Debug, Clone)]
+pub struct Condensation<K> {
+    pub components: Vec<Vec<K>>,
+    pub component_of: HashMap<K, usize>,
+    pub edges: Vec<HashSet<usize>>,
+}
+
+pub fn condensation<K, E>(graph: &HashMap<K, E>) -> Condensation<K>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    E: Neighbors<K>,
+{
+    let components = strongly_connected_components(graph);
+    let component_of = components
+        .iter()
+        .enumerate()
+        .flat_map(|(index, component)| component.iter().map(move |key| (key.clone(), index)))
+        .collect::<HashMap<K, usize>>();
+    let mut edges = vec![HashSet::new(); components.len()];
+    for (node, neighbors) in graph {
+        let from = component_of[node];
+        for neighbor in neighbors.neighbors() {
+            let to = component_of[neighbor];
+            if from != to {
+                edges[from].insert(to);
+            }
+        }
+    }
+    Condensation {
+        components,
+        component_of,
+        edges,
+    }
+}
+
+// A cycle that keeps an algorithm from finishing, like a topological sort, or shortest paths with a
+// cycle of negative cost
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleFound<K> {
+    // Each key has an edge to the next one, and the last key has an edge to the first
+    pub cycle: Vec<K>,
+}
+
+// Every node comes before all the nodes it has edges to
+pub fn topological_sort<K, E>(graph: &HashMap<K, E>) -> Result<Vec<K>, CycleFound<K>>
+where
+    K: PartialEq + Eq + Hash + Clone,
+    E: Neighbors<K>,
+{
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    enum Mark {
+        Unvisited,
+        InProgress,
+        Done,
+    }
+
+    let (keys, adjacency) = index_graph(graph);
+    let mut marks = vec![Mark::Unvisited; keys.len()];
+    let mut finished = Vec::with_capacity(keys.len());
+    for root in 0..keys.len() {
+        if marks[root] != Mark::Unvisited {
+            continue;
+        }
+        marks[root] = Mark::InProgress;
+        let mut call_stack = vec![(root, 0)];
+        while let Some((node, edge_position)) = call_stack.last_mut() {
+            let node = *node;
+            let Some(&neighbor) = adjacency[node].get(*edge_position) else {
+                marks[node] = Mark::Done;
+                finished.push(node);
+                call_stack.pop();
+                continue;
+            };
+            *edge_position += 1;
+            match marks[neighbor] {
+                Mark::Unvisited => {
+                    marks[neighbor] = Mark::InProgress;
+                    call_stack.push((neighbor, 0));
+                }
+                Mark::InProgress => {
+                    // The call stack is the path from the root, so the cycle is its tail
+                    let start = call_stack
+                        .iter()
+                        .position(|&(in_stack, _)| in_stack == neighbor)
+                        .unwrap();
+                    let cycle = call_stack[start..]
+                        .iter()
+                        .map(|&(in_stack, _)| keys[in_stack].clone())
+                        .collect();
+                    return Err(CycleFound { cycle });
+                }
+                Mark::Done => {}
+            }
+        }
+    }
+    finished.reverse();
+    Ok(finished
+        .into_iter()
+        .map(|index| keys[index].clone())
+        .collect())
+}
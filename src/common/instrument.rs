@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+#[derive(Debug, Default, Clone)]
+pub struct InstrumentStats {
+    pub pushed: usize,
+    pub popped: usize,
+    // Entries that were taken out of the frontier without being considered, because a better entry
+    // for the same key was already considered
+    pub stale_pops: usize,
+    // Entries that were skipped because nothing reached through them could beat the best result
+    // found so far
+    pub pruned: usize,
+    pub peak_frontier: usize,
+    pub peak_visited: usize,
+    // From the first push to the last pop
+    pub elapsed: Duration,
+}
+
+#[cfg(feature = "search-stats")]
+mod imp {
+    use std::time::Instant;
+
+    use super::InstrumentStats;
+
+    #[derive(Debug, Default)]
+    pub struct Instrument {
+        stats: InstrumentStats,
+        frontier: usize,
+        visited: usize,
+        started: Option<Instant>,
+    }
+
+    impl Instrument {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        fn tick(&mut self) {
+            if let Some(started) = self.started {
+                self.stats.elapsed = started.elapsed();
+            }
+        }
+
+        pub fn on_push(&mut self) {
+            self.started.get_or_insert_with(Instant::now);
+            self.stats.pushed += 1;
+            self.frontier += 1;
+            self.stats.peak_frontier = self.stats.peak_frontier.max(self.frontier);
+        }
+
+        pub fn on_pop(&mut self) {
+            self.stats.popped += 1;
+            self.frontier = self.frontier.saturating_sub(1);
+            self.tick();
+        }
+
+        pub fn on_stale_pop(&mut self) {
+            self.stats.stale_pops += 1;
+            self.frontier = self.frontier.saturating_sub(1);
+            self.tick();
+        }
+
+        pub fn on_prune(&mut self) {
+            self.stats.pruned += 1;
+            self.frontier = self.frontier.saturating_sub(1);
+            self.tick();
+        }
+
+        pub fn on_discover(&mut self) {
+            self.visited += 1;
+            self.stats.peak_visited = self.stats.peak_visited.max(self.visited);
+        }
+
+        pub fn on_forget(&mut self) {
+            self.visited = self.visited.saturating_sub(1);
+        }
+
+        pub fn stats(&self) -> Option<InstrumentStats> {
+            Some(self.stats.clone())
+        }
+    }
+}
+
+#[cfg(not(feature = "search-stats"))]
+mod imp {
+    use super::InstrumentStats;
+
+    #[derive(Debug, Default)]
+    pub struct Instrument;
+
+    impl Instrument {
+        pub fn new() -> Self {
+            Self
+        }
+
+        #[inline(always)]
+        pub fn on_push(&mut self) {}
+
+        #[inline(always)]
+        pub fn on_pop(&mut self) {}
+
+        #[inline(always)]
+        pub fn on_stale_pop(&mut self) {}
+
+        #[inline(always)]
+        pub fn on_prune(&mut self) {}
+
+        #[inline(always)]
+        pub fn on_discover(&mut self) {}
+
+        #[inline(always)]
+        pub fn on_forget(&mut self) {}
+
+        pub fn stats(&self) -> Option<InstrumentStats> {
+            None
+        }
+    }
+}
+
+pub use imp::Instrument;
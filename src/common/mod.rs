@@ -1,5 +1,15 @@
 pub mod bfs;
+pub mod cycle;
 pub mod dfs;
 pub mod direction;
+pub mod disjoint_set;
+pub mod dominators;
+pub mod dot;
+pub mod flow;
+pub mod graph;
+pub mod instrument;
 pub mod num_warp;
+pub mod number_theory;
+pub mod search;
+pub mod stencil;
 pub mod vmatrix;
@@ -0,0 +1,148 @@
+use itertools::Itertools;
+use num::{Integer, Signed};
+
+// Returns `(gcd, x, y)` such that `a * x + b * y == gcd`
+pub fn extended_gcd<N>(a: N, b: N) -> (N, N, N)
+where
+    N: Clone + Integer + Signed,
+{
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (N::one(), N::zero());
+    let (mut old_y, mut y) = (N::zero(), N::one());
+    while !r.is_zero() {
+        let quotient = old_r.div_floor(&r);
+        let next_r = old_r - quotient.clone() * r.clone();
+        old_r = std::mem::replace(&mut r, next_r);
+        let next_x = old_x - quotient.clone() * x.clone();
+        old_x = std::mem::replace(&mut x, next_x);
+        let next_y = old_y - quotient * y.clone();
+        old_y = std::mem::replace(&mut y, next_y);
+    }
+    if old_r.is_negative() {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+// All the numbers that leave `residue` when divided by `modulus`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Congruence {
+    pub residue: usize,
+    pub modulus: usize,
+}
+
+impl Congruence {
+    pub fn new(residue: usize, modulus: usize) -> Self {
+        assert!(0 < modulus, "Modulus must be positive");
+        Self {
+            residue: residue % modulus,
+            modulus,
+        }
+    }
+
+    pub fn contains(&self, number: usize) -> bool {
+        number % self.modulus == self.residue
+    }
+
+    // The smallest number in the congruence that is not smaller than `min`
+    pub fn first_from(&self, min: usize) -> usize {
+        if min <= self.residue {
+            self.residue
+        } else {
+            min + (self.residue + self.modulus - min % self.modulus) % self.modulus
+        }
+    }
+
+    // The moduli don't have to be coprime. Returns None if no number satisfies both.
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let [r1, m1, r2, m2] = [self.residue, self.modulus, other.residue, other.modulus]
+            .map(|n| i128::try_from(n).unwrap());
+        let (gcd, x, _) = extended_gcd(m1, m2);
+        let difference = r2 - r1;
+        if difference % gcd != 0 {
+            return None;
+        }
+        let modulus = m1 / gcd * m2;
+        // m1 * x is 1 modulo m2 / gcd, so this fixes the remainder modulo m2 without changing it
+        // modulo m1
+        let steps = (difference / gcd * x).rem_euclid(m2 / gcd);
+        let residue = (r1 + m1 * steps).rem_euclid(modulus);
+        Some(Self {
+            residue: usize::try_from(residue).unwrap(),
+            modulus: usize::try_from(modulus).expect("Combined modulus too large"),
+        })
+    }
+}
+
+// The Chinese Remainder Theorem, for moduli that may share factors
+pub fn crt(congruences: impl IntoIterator<Item = Congruence>) -> Option<Congruence> {
+    congruences
+        .into_iter()
+        .try_fold(Congruence::new(0, 1), |joined, congruence| {
+            joined.intersect(&congruence)
+        })
+}
+
+// The times at which something happens in a process that eventually repeats. Before
+// `cycle_start` the hits are listed one by one - from there on they repeat every `period`.
+#[derive(Debug, Clone)]
+pub struct PeriodicHits {
+    pub prefix_hits: Vec<usize>,
+    pub cycle_start: usize,
+    pub period: usize,
+    // Between `cycle_start` (inclusive) and `cycle_start + period` (exclusive)
+    pub cycle_hits: Vec<usize>,
+}
+
+impl PeriodicHits {
+    pub fn contains(&self, time: usize) -> bool {
+        if time < self.cycle_start {
+            self.prefix_hits.contains(&time)
+        } else {
+            let in_cycle = self.cycle_start + (time - self.cycle_start) % self.period;
+            self.cycle_hits.contains(&in_cycle)
+        }
+    }
+}
+
+// The first time at which all of the processes hit together
+pub fn first_common_hit(all_hits: &[PeriodicHits]) -> Option<usize> {
+    let all_periodic_from = all_hits.iter().map(|hits| hits.cycle_start).max()?;
+
+    // Before they are all periodic, a common hit must be one of the listed hits of the process
+    // that becomes periodic last
+    let last_to_cycle = all_hits
+        .iter()
+        .find(|hits| hits.cycle_start == all_periodic_from)
+        .unwrap();
+    let early_hit = last_to_cycle
+        .prefix_hits
+        .iter()
+        .copied()
+        .filter(|&time| all_hits.iter().all(|hits| hits.contains(time)))
+        .min();
+    if early_hit.is_some() {
+        return early_hit;
+    }
+
+    // Each process contributes one congruence per hit, and any combination of them may work
+    let mut joined = vec![Congruence::new(0, 1)];
+    for hits in all_hits {
+        let congruences = hits
+            .cycle_hits
+            .iter()
+            .map(|&time| Congruence::new(time, hits.period))
+            .collect::<Vec<_>>();
+        joined = joined
+            .iter()
+            .cartesian_product(congruences.iter())
+            .filter_map(|(joined, congruence)| joined.intersect(congruence))
+            .unique()
+            .collect();
+    }
+    joined
+        .into_iter()
+        .map(|congruence| congruence.first_from(all_periodic_from))
+        .min()
+}
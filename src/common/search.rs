@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::Add;
+
+use num::Zero;
+
+use crate::common::bfs::{BucketBfs, HashMapBfs};
+use crate::common::dfs::{depth_limited_dfs, DepthLimitedResult};
+
+pub trait SearchProblem {
+    type State: Eq + Hash + Clone;
+    type Cost: Ord + Clone + Zero;
+
+    fn initial_states(&self) -> impl Iterator<Item = Self::State>;
+
+    fn successors(&self, state: &Self::State) -> impl Iterator<Item = (Self::State, Self::Cost)>;
+
+    fn is_goal(&self, state: &Self::State) -> bool;
+
+    // Only used by A*. Must never overestimate the cost of reaching a goal.
+    fn heuristic(&self, _state: &Self::State) -> Self::Cost {
+        Self::Cost::zero()
+    }
+}
+
+// With small integer costs, `search_buckets` does the same as Dijkstra and AStar without a heap
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchStrategy {
+    // Finds the path with the fewest edges, regardless of their costs
+    Bfs,
+    Dijkstra,
+    AStar,
+    // Finds some path, not necessarily the cheapest
+    Dfs,
+    // Like Bfs, but only keeps the current path in memory
+    IterativeDeepening,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SearchStats {
+    pub expanded: usize,
+    pub generated: usize,
+}
+
+#[derive(Debug)]
+pub struct SearchResult<S, C> {
+    pub cost: Option<C>,
+    pub path: Vec<S>,
+    pub stats: SearchStats,
+}
+
+impl<S, C> SearchResult<S, C> {
+    fn not_found(stats: SearchStats) -> Self {
+        Self {
+            cost: None,
+            path: Vec::new(),
+            stats,
+        }
+    }
+}
+
+pub fn search<P>(problem: &P, strategy: SearchStrategy) -> SearchResult<P::State, P::Cost>
+where
+    P: SearchProblem,
+    for<'a> &'a P::Cost: Add<&'a P::Cost, Output = P::Cost>,
+{
+    match strategy {
+        SearchStrategy::Bfs => search_bfs(problem),
+        SearchStrategy::Dijkstra => search_best_first(problem, HashMapBfs::default()),
+        SearchStrategy::AStar => search_best_first(
+            problem,
+            HashMapBfs::with_heuristic(|state: &P::State| problem.heuristic(state)),
+        ),
+        SearchStrategy::Dfs => search_dfs(problem),
+        SearchStrategy::IterativeDeepening => search_iterative_deepening(problem),
+    }
+}
+
+// A* with Dial's buckets instead of a heap. Only for small integer costs - see `BucketBfs`. Pass
+// a problem without a heuristic to get Dijkstra.
+pub fn search_buckets<P>(problem: &P) -> SearchResult<P::State, usize>
+where
+    P: SearchProblem<Cost = usize>,
+{
+    let mut stats = SearchStats::default();
+    let mut bfs = BucketBfs::with_heuristic(|state: &P::State| problem.heuristic(state));
+    for state in problem.initial_states() {
+        bfs.add_root(state, 0);
+    }
+    while let Some(state) = bfs.consider_next() {
+        if problem.is_goal(&state) {
+            return SearchResult {
+                cost: bfs.cost(&state).cloned(),
+                path: bfs.path_to(&state),
+                stats,
+            };
+        }
+        stats.expanded += 1;
+        for (next_state, cost) in problem.successors(&state) {
+            stats.generated += 1;
+            bfs.add_edge(state.clone(), next_state, cost);
+        }
+    }
+    SearchResult::not_found(stats)
+}
+
+fn search_best_first<P, H>(
+    problem: &P,
+    mut bfs: HashMapBfs<P::State, P::Cost, H>,
+) -> SearchResult<P::State, P::Cost>
+where
+    P: SearchProblem,
+    for<'a> &'a P::Cost: Add<&'a P::Cost, Output = P::Cost>,
+    H: Fn(&P::State) -> P::Cost,
+{
+    let mut stats = SearchStats::default();
+    for state in problem.initial_states() {
+        bfs.add_root(state, P::Cost::zero());
+    }
+    while let Some(state) = bfs.consider_next() {
+        if problem.is_goal(&state) {
+            return SearchResult {
+                cost: bfs.cost(&state).cloned(),
+                path: bfs.path_to(&state),
+                stats,
+            };
+        }
+        stats.expanded += 1;
+        for (next_state, cost) in problem.successors(&state) {
+            stats.generated += 1;
+            bfs.add_edge(state.clone(), next_state, cost);
+        }
+    }
+    SearchResult::not_found(stats)
+}
+
+fn search_bfs<P>(problem: &P) -> SearchResult<P::State, P::Cost>
+where
+    P: SearchProblem,
+    for<'a> &'a P::Cost: Add<&'a P::Cost, Output = P::Cost>,
+{
+    let mut stats = SearchStats::default();
+    let mut bfs = HashMapBfs::<P::State, usize>::default();
+    // The BFS itself only counts edges, so the actual costs are tracked separately
+    let mut costs = HashMap::new();
+    for state in problem.initial_states() {
+        costs.insert(state.clone(), P::Cost::zero());
+        bfs.add_root(state, 0);
+    }
+    while let Some(state) = bfs.consider_next() {
+        if problem.is_goal(&state) {
+            return SearchResult {
+                cost: costs.remove(&state),
+                path: bfs.path_to(&state),
+                stats,
+            };
+        }
+        stats.expanded += 1;
+        for (next_state, cost) in problem.successors(&state) {
+            stats.generated += 1;
+            if bfs.add_edge(state.clone(), next_state.clone(), 1) {
+                costs.insert(next_state, &costs[&state] + &cost);
+            }
+        }
+    }
+    SearchResult::not_found(stats)
+}
+
+fn search_dfs<P>(problem: &P) -> SearchResult<P::State, P::Cost>
+where
+    P: SearchProblem,
+    for<'a> &'a P::Cost: Add<&'a P::Cost, Output = P::Cost>,
+{
+    let mut stats = SearchStats::default();
+    let mut visited = HashMap::<P::State, (Option<P::State>, P::Cost)>::new();
+    let mut stack = problem
+        .initial_states()
+        .map(|state| (state, None, P::Cost::zero()))
+        .collect::<Vec<_>>();
+    stack.reverse();
+    while let Some((state, parent, cost)) = stack.pop() {
+        if visited.contains_key(&state) {
+            continue;
+        }
+        visited.insert(state.clone(), (parent, cost.clone()));
+        if problem.is_goal(&state) {
+            let mut path = vec![state];
+            while let Some(parent) = visited[path.last().unwrap()].0.clone() {
+                path.push(parent);
+            }
+            path.reverse();
+            return SearchResult {
+                cost: Some(cost),
+                path,
+                stats,
+            };
+        }
+        stats.expanded += 1;
+        for (next_state, additional_cost) in problem.successors(&state) {
+            stats.generated += 1;
+            if !visited.contains_key(&next_state) {
+                stack.push((next_state, Some(state.clone()), &cost + &additional_cost));
+            }
+        }
+    }
+    SearchResult::not_found(stats)
+}
+
+fn search_iterative_deepening<P>(problem: &P) -> SearchResult<P::State, P::Cost>
+where
+    P: SearchProblem,
+    for<'a> &'a P::Cost: Add<&'a P::Cost, Output = P::Cost>,
+{
+    let mut stats = SearchStats::default();
+    for depth_limit in 0.. {
+        let mut cut_off = false;
+        for state in problem.initial_states() {
+            let result = depth_limited_dfs(
+                state,
+                P::Cost::zero(),
+                depth_limit,
+                |state| {
+                    stats.expanded += 1;
+                    let successors = problem.successors(state).collect::<Vec<_>>();
+                    stats.generated += successors.len();
+                    successors
+                },
+                |state| problem.is_goal(state),
+            );
+            match result {
+                DepthLimitedResult::Found { cost, path } => {
+                    return SearchResult {
+                        cost: Some(cost),
+                        path,
+                        stats,
+                    };
+                }
+                DepthLimitedResult::CutOff => {
+                    cut_off = true;
+                }
+                DepthLimitedResult::Exhausted => {}
+            }
+        }
+        if !cut_off {
+            break;
+        }
+    }
+    SearchResult::not_found(stats)
+}
@@ -0,0 +1,48 @@
+// Motion vectors for `VMatrix::motions` and the like, as `[row, col]` offsets. None of them
+// include the cell itself.
+
+pub const ORTHOGONAL: [[isize; 2]; 4] = [[-1, 0], [0, 1], [1, 0], [0, -1]];
+
+pub const DIAGONAL: [[isize; 2]; 4] = [[-1, -1], [-1, 1], [1, 1], [1, -1]];
+
+pub const EIGHT_NEIGHBORS: [[isize; 2]; 8] = [
+    [-1, -1],
+    [-1, 0],
+    [-1, 1],
+    [0, 1],
+    [1, 1],
+    [1, 0],
+    [1, -1],
+    [0, -1],
+];
+
+pub const KNIGHT: [[isize; 2]; 8] = [
+    [-2, -1],
+    [-2, 1],
+    [-1, 2],
+    [1, 2],
+    [2, 1],
+    [2, -1],
+    [1, -2],
+    [-1, -2],
+];
+
+fn ball(radius: usize, distance: impl Fn([isize; 2]) -> usize) -> Vec<[isize; 2]> {
+    let radius = radius as isize;
+    (-radius..=radius)
+        .flat_map(|row| (-radius..=radius).map(move |col| [row, col]))
+        .filter(|&vec| vec != [0, 0] && distance(vec) <= radius as usize)
+        .collect()
+}
+
+// Everything up to `radius` orthogonal steps away
+pub fn manhattan_ball(radius: usize) -> Vec<[isize; 2]> {
+    ball(radius, |[row, col]| row.unsigned_abs() + col.unsigned_abs())
+}
+
+// Everything up to `radius` steps away when diagonal steps are allowed
+pub fn chebyshev_ball(radius: usize) -> Vec<[isize; 2]> {
+    ball(radius, |[row, col]| {
+        row.unsigned_abs().max(col.unsigned_abs())
+    })
+}
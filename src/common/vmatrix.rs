@@ -1,5 +1,7 @@
 use std::fmt::{Display, Write};
-use std::ops::{Index, IndexMut};
+use std::ops::{Index, IndexMut, Range};
+
+use crate::common::stencil;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct VMatrix<T> {
@@ -8,23 +10,87 @@ pub struct VMatrix<T> {
     pub values: Vec<T>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VMatrixParseError<E> {
+    Empty,
+    RaggedRow {
+        row: usize,
+        expected_cols: usize,
+        found_cols: usize,
+    },
+    // Usually means the input has Windows line endings
+    CarriageReturn {
+        row: usize,
+        col: usize,
+    },
+    InvalidChar {
+        row: usize,
+        col: usize,
+        ch: char,
+        error: E,
+    },
+}
+
+impl<E: Display> Display for VMatrixParseError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VMatrixParseError::Empty => write!(f, "Empty matrix"),
+            VMatrixParseError::RaggedRow {
+                row,
+                expected_cols,
+                found_cols,
+            } => write!(
+                f,
+                "Row {row} has {found_cols} columns, but previous rows have {expected_cols}"
+            ),
+            VMatrixParseError::CarriageReturn { row, col } => {
+                write!(f, "Stray \\r at row {row}, column {col}")
+            }
+            VMatrixParseError::InvalidChar {
+                row,
+                col,
+                ch,
+                error,
+            } => write!(f, "Invalid char {ch:?} at row {row}, column {col}: {error}"),
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + Display> std::error::Error for VMatrixParseError<E> {}
+
+// Each None ends a row. Panics if the rows are not all the same length.
 impl<T> FromIterator<Option<T>> for VMatrix<T> {
     fn from_iter<I: IntoIterator<Item = Option<T>>>(iter: I) -> Self {
-        let mut cols = 0;
+        let mut cols = None;
+        let mut rows = 0;
         let mut cur_col = 0;
-        let values = iter
-            .into_iter()
-            .filter_map(|item| {
-                if item.is_none() {
-                    cols = cur_col;
-                    cur_col = 0;
-                } else {
+        let mut values = Vec::new();
+        let mut end_row = |cur_col: &mut usize| {
+            match cols {
+                None => cols = Some(*cur_col),
+                Some(cols) => assert_eq!(
+                    cols, *cur_col,
+                    "Row {rows} has {cur_col} columns, but previous rows have {cols}"
+                ),
+            }
+            rows += 1;
+            *cur_col = 0;
+        };
+        for item in iter {
+            match item {
+                Some(value) => {
+                    values.push(value);
                     cur_col += 1;
                 }
-                item
-            })
-            .collect::<Vec<T>>();
-        let rows = values.len() / cols;
+                None => end_row(&mut cur_col),
+            }
+        }
+        // The last row does not have to be terminated
+        if 0 < cur_col {
+            end_row(&mut cur_col);
+        }
+        let cols = cols.unwrap_or(0);
+        let rows = if cols == 0 { 0 } else { rows };
         Self { cols, rows, values }
     }
 }
@@ -41,22 +107,54 @@ impl<T> VMatrix<T> {
     }
 
     pub fn from_chars(input: &str, mut mapper: impl FnMut([usize; 2], char) -> T) -> Self {
-        let mut row = 0;
-        let mut col = 0;
-        input
-            .chars()
-            .map(move |ch| {
-                if ch == '\n' {
-                    row += 1;
-                    col = 0;
-                    None
-                } else {
-                    let result = mapper([row, col], ch);
-                    col += 1;
-                    Some(result)
+        Self::try_from_chars(input, |coord, ch| {
+            Ok::<_, std::convert::Infallible>(mapper(coord, ch))
+        })
+        .unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    // A single trailing newline is allowed
+    pub fn try_from_chars<E>(
+        input: &str,
+        mut mapper: impl FnMut([usize; 2], char) -> Result<T, E>,
+    ) -> Result<Self, VMatrixParseError<E>> {
+        let input = input.strip_suffix('\n').unwrap_or(input);
+        let mut cols = None;
+        let mut values = Vec::new();
+        let mut rows = 0;
+        for (row, line) in input.split('\n').enumerate() {
+            let mut col = 0;
+            for ch in line.chars() {
+                if ch == '\r' {
+                    return Err(VMatrixParseError::CarriageReturn { row, col });
+                }
+                let value =
+                    mapper([row, col], ch).map_err(|error| VMatrixParseError::InvalidChar {
+                        row,
+                        col,
+                        ch,
+                        error,
+                    })?;
+                values.push(value);
+                col += 1;
+            }
+            match cols {
+                None => cols = Some(col),
+                Some(cols) if cols != col => {
+                    return Err(VMatrixParseError::RaggedRow {
+                        row,
+                        expected_cols: cols,
+                        found_cols: col,
+                    });
                 }
-            })
-            .collect()
+                Some(_) => {}
+            }
+            rows += 1;
+        }
+        match cols {
+            None | Some(0) => Err(VMatrixParseError::Empty),
+            Some(cols) => Ok(Self { cols, rows, values }),
+        }
     }
 
     pub fn map<S>(&self, mut dlg: impl FnMut(usize, &T) -> S) -> VMatrix<S> {
@@ -112,21 +210,11 @@ impl<T> VMatrix<T> {
     }
 
     pub fn neighbors_no_diag(&self, node: usize) -> impl '_ + Iterator<Item = usize> {
-        let x = (node % self.cols) as isize;
-        let y = (node / self.cols) as isize;
-        [[0, -1], [1, 0], [0, 1], [-1, 0]]
-            .into_iter()
-            .filter_map(move |[dx, dy]: [isize; 2]| {
-                let x = x + dx;
-                if x < 0 || self.cols as isize <= x {
-                    return None;
-                }
-                let y = y + dy;
-                if y < 0 || self.rows as isize <= y {
-                    return None;
-                }
-                Some(x as usize + y as usize * self.cols)
-            })
+        self.motion_indices(self.index_to_coord(node).unwrap(), stencil::ORTHOGONAL)
+    }
+
+    pub fn neighbors_with_diag(&self, node: usize) -> impl '_ + Iterator<Item = usize> {
+        self.motion_indices(self.index_to_coord(node).unwrap(), stencil::EIGHT_NEIGHBORS)
     }
 
     pub fn coord_to_index(&self, [row, col]: [usize; 2]) -> Option<usize> {
@@ -186,12 +274,402 @@ impl<T> VMatrix<T> {
             .filter_map(move |vec| self.motion(start, vec).ok())
     }
 
+    // With small matrices or large vectors, several vectors may wrap to the same coord
+    pub fn motions_wrap<'a>(
+        &'a self,
+        start: [usize; 2],
+        vecs: impl 'a + IntoIterator<Item = [isize; 2]>,
+    ) -> impl 'a + Iterator<Item = [usize; 2]> {
+        vecs.into_iter()
+            .map(move |vec| self.motion_wrap(start, vec))
+    }
+
+    pub fn motion_indices<'a>(
+        &'a self,
+        start: [usize; 2],
+        vecs: impl 'a + IntoIterator<Item = [isize; 2]>,
+    ) -> impl 'a + Iterator<Item = usize> {
+        self.motions(start, vecs)
+            .map(|coord| self.coord_to_index(coord).unwrap())
+    }
+
+    pub fn motion_indices_wrap<'a>(
+        &'a self,
+        start: [usize; 2],
+        vecs: impl 'a + IntoIterator<Item = [isize; 2]>,
+    ) -> impl 'a + Iterator<Item = usize> {
+        self.motions_wrap(start, vecs)
+            .map(|coord| self.coord_to_index(coord).unwrap())
+    }
+
+    pub fn motion_values<'a>(
+        &'a self,
+        start: [usize; 2],
+        vecs: impl 'a + IntoIterator<Item = [isize; 2]>,
+    ) -> impl 'a + Iterator<Item = ([usize; 2], &'a T)> {
+        self.motions(start, vecs).map(|coord| (coord, &self[coord]))
+    }
+
+    pub fn motion_values_wrap<'a>(
+        &'a self,
+        start: [usize; 2],
+        vecs: impl 'a + IntoIterator<Item = [isize; 2]>,
+    ) -> impl 'a + Iterator<Item = ([usize; 2], &'a T)> {
+        self.motions_wrap(start, vecs)
+            .map(|coord| (coord, &self[coord]))
+    }
+
     pub fn iter(&self) -> impl '_ + Iterator<Item = ([usize; 2], &T)> {
         self.values
             .iter()
             .enumerate()
             .map(|(i, value)| ([i / self.cols, i % self.cols], value))
     }
+
+    pub fn row(&self, row: usize) -> std::slice::Iter<'_, T> {
+        assert!(row < self.rows, "Invalid row {row}");
+        self.values[(row * self.cols)..((row + 1) * self.cols)].iter()
+    }
+
+    pub fn row_mut(&mut self, row: usize) -> std::slice::IterMut<'_, T> {
+        assert!(row < self.rows, "Invalid row {row}");
+        self.values[(row * self.cols)..((row + 1) * self.cols)].iter_mut()
+    }
+
+    pub fn col(&self, col: usize) -> std::iter::StepBy<std::slice::Iter<'_, T>> {
+        assert!(col < self.cols, "Invalid col {col}");
+        self.values[col..].iter().step_by(self.cols)
+    }
+
+    pub fn col_mut(&mut self, col: usize) -> std::iter::StepBy<std::slice::IterMut<'_, T>> {
+        assert!(col < self.cols, "Invalid col {col}");
+        self.values[col..].iter_mut().step_by(self.cols)
+    }
+
+    pub fn rows_iter(&self) -> impl '_ + Iterator<Item = std::slice::Iter<'_, T>> {
+        (0..self.rows).map(|row| self.row(row))
+    }
+
+    pub fn cols_iter(
+        &self,
+    ) -> impl '_ + Iterator<Item = std::iter::StepBy<std::slice::Iter<'_, T>>> {
+        (0..self.cols).map(|col| self.col(col))
+    }
+
+    pub fn oriented(&self, orientation: Orientation) -> VMatrixView<'_, T> {
+        let [rows, cols] = orientation.view_dims([self.rows, self.cols]);
+        VMatrixView {
+            matrix: self,
+            orientation,
+            offset: [0, 0],
+            rows,
+            cols,
+        }
+    }
+
+    pub fn oriented_mut(&mut self, orientation: Orientation) -> VMatrixViewMut<'_, T> {
+        let [rows, cols] = orientation.view_dims([self.rows, self.cols]);
+        VMatrixViewMut {
+            matrix: self,
+            orientation,
+            offset: [0, 0],
+            rows,
+            cols,
+        }
+    }
+
+    // The rectangle between the corners, not including the end corner's row and column
+    pub fn view(&self, range: Range<[usize; 2]>) -> VMatrixView<'_, T> {
+        self.oriented(Orientation::IDENTITY).view(range)
+    }
+
+    pub fn view_mut(&mut self, range: Range<[usize; 2]>) -> VMatrixViewMut<'_, T> {
+        self.oriented_mut(Orientation::IDENTITY).view_mut(range)
+    }
+
+    // Every rectangle of the given size, along with its top left corner
+    pub fn windows(
+        &self,
+        size: [usize; 2],
+    ) -> impl '_ + Iterator<Item = ([usize; 2], VMatrixView<'_, T>)> {
+        self.oriented(Orientation::IDENTITY).windows(size)
+    }
+}
+
+// One of the eight ways to turn and mirror a matrix. Views flip the coordinates they get and then
+// transpose them to find the cell in the underlying matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Orientation {
+    transpose: bool,
+    flip_rows: bool,
+    flip_cols: bool,
+}
+
+impl Orientation {
+    pub const IDENTITY: Self = Self::new(false, false, false);
+    pub const TRANSPOSE: Self = Self::new(true, false, false);
+    // Rotations are clockwise
+    pub const ROTATE_90: Self = Self::new(true, false, true);
+    pub const ROTATE_180: Self = Self::new(false, true, true);
+    pub const ROTATE_270: Self = Self::new(true, true, false);
+    // Left becomes right
+    pub const FLIP_HORIZONTAL: Self = Self::new(false, false, true);
+    // Top becomes bottom
+    pub const FLIP_VERTICAL: Self = Self::new(false, true, false);
+
+    const fn new(transpose: bool, flip_rows: bool, flip_cols: bool) -> Self {
+        Self {
+            transpose,
+            flip_rows,
+            flip_cols,
+        }
+    }
+
+    // Turning a matrix by `self` and then turning the result by `other`
+    pub fn then(self, other: Self) -> Self {
+        // Flipping after a transpose is flipping the other dimension before it
+        let [flip_rows, flip_cols] = if other.transpose {
+            [self.flip_cols, self.flip_rows]
+        } else {
+            [self.flip_rows, self.flip_cols]
+        };
+        Self {
+            transpose: self.transpose != other.transpose,
+            flip_rows: flip_rows != other.flip_rows,
+            flip_cols: flip_cols != other.flip_cols,
+        }
+    }
+
+    fn view_dims(self, [rows, cols]: [usize; 2]) -> [usize; 2] {
+        if self.transpose {
+            [cols, rows]
+        } else {
+            [rows, cols]
+        }
+    }
+
+    fn matrix_coord(self, [row, col]: [usize; 2], [rows, cols]: [usize; 2]) -> [usize; 2] {
+        let row = if self.flip_rows { rows - 1 - row } else { row };
+        let col = if self.flip_cols { cols - 1 - col } else { col };
+        if self.transpose {
+            [col, row]
+        } else {
+            [row, col]
+        }
+    }
+
+    // Returns the offset and the dimensions of the sub-view. The offset is in the coordinates of
+    // the underlying matrix, the dimensions in those of the view.
+    fn sub_view(
+        self,
+        offset: [usize; 2],
+        dims: [usize; 2],
+        Range { start, end }: Range<[usize; 2]>,
+    ) -> ([usize; 2], [usize; 2]) {
+        assert!(
+            (0..2).all(|i| start[i] <= end[i] && end[i] <= dims[i]),
+            "Invalid view range {start:?}..{end:?}"
+        );
+        let sub_dims = [end[0] - start[0], end[1] - start[1]];
+        if sub_dims.contains(&0) {
+            return (offset, sub_dims);
+        }
+        // The corner closest to the matrix's origin may be any of the view's corners
+        let [first, last] =
+            [start, [end[0] - 1, end[1] - 1]].map(|corner| self.matrix_coord(corner, dims));
+        let sub_offset = [0, 1].map(|i| offset[i] + first[i].min(last[i]));
+        (sub_offset, sub_dims)
+    }
+}
+
+// A turned, mirrored or partial matrix that does not copy the values
+#[derive(Debug)]
+pub struct VMatrixView<'a, T> {
+    matrix: &'a VMatrix<T>,
+    orientation: Orientation,
+    offset: [usize; 2],
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl<T> Clone for VMatrixView<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for VMatrixView<'_, T> {}
+
+impl<'a, T> VMatrixView<'a, T> {
+    // The coordinates in the underlying matrix
+    pub fn matrix_coord(&self, [row, col]: [usize; 2]) -> Option<[usize; 2]> {
+        if row < self.rows && col < self.cols {
+            let [row, col] = self
+                .orientation
+                .matrix_coord([row, col], [self.rows, self.cols]);
+            Some([self.offset[0] + row, self.offset[1] + col])
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, coord: [usize; 2]) -> Option<&'a T> {
+        self.matrix.get(self.matrix_coord(coord)?)
+    }
+
+    pub fn oriented(&self, orientation: Orientation) -> Self {
+        let [rows, cols] = orientation.view_dims([self.rows, self.cols]);
+        Self {
+            orientation: self.orientation.then(orientation),
+            rows,
+            cols,
+            ..*self
+        }
+    }
+
+    pub fn view(&self, range: Range<[usize; 2]>) -> Self {
+        let (offset, [rows, cols]) =
+            self.orientation
+                .sub_view(self.offset, [self.rows, self.cols], range);
+        Self {
+            offset,
+            rows,
+            cols,
+            ..*self
+        }
+    }
+
+    pub fn windows(
+        &self,
+        [rows, cols]: [usize; 2],
+    ) -> impl 'a + Iterator<Item = ([usize; 2], Self)> {
+        let view = *self;
+        (0..(self.rows + 1).saturating_sub(rows)).flat_map(move |row| {
+            (0..(view.cols + 1).saturating_sub(cols))
+                .map(move |col| ([row, col], view.view([row, col]..[row + rows, col + cols])))
+        })
+    }
+
+    pub fn iter(&self) -> impl 'a + Iterator<Item = ([usize; 2], &'a T)> {
+        let view = *self;
+        (0..self.rows).flat_map(move |row| {
+            (0..view.cols).map(move |col| ([row, col], view.get([row, col]).unwrap()))
+        })
+    }
+
+    pub fn row(&self, row: usize) -> impl 'a + Iterator<Item = &'a T> {
+        assert!(row < self.rows, "Invalid row {row}");
+        let view = *self;
+        (0..self.cols).map(move |col| view.get([row, col]).unwrap())
+    }
+
+    pub fn col(&self, col: usize) -> impl 'a + Iterator<Item = &'a T> {
+        assert!(col < self.cols, "Invalid col {col}");
+        let view = *self;
+        (0..self.rows).map(move |row| view.get([row, col]).unwrap())
+    }
+
+    pub fn rows_iter(&self) -> impl 'a + Iterator<Item = impl 'a + Iterator<Item = &'a T>> {
+        let view = *self;
+        (0..self.rows).map(move |row| view.row(row))
+    }
+
+    pub fn cols_iter(&self) -> impl 'a + Iterator<Item = impl 'a + Iterator<Item = &'a T>> {
+        let view = *self;
+        (0..self.cols).map(move |col| view.col(col))
+    }
+
+    pub fn to_matrix(&self) -> VMatrix<T>
+    where
+        T: Clone,
+    {
+        VMatrix::new(self.rows, self.cols, |coord| self[coord].clone())
+    }
+}
+
+impl<T> Index<[usize; 2]> for VMatrixView<'_, T> {
+    type Output = T;
+
+    fn index(&self, coord: [usize; 2]) -> &Self::Output {
+        self.get(coord).expect("Invalid coords")
+    }
+}
+
+#[derive(Debug)]
+pub struct VMatrixViewMut<'a, T> {
+    matrix: &'a mut VMatrix<T>,
+    orientation: Orientation,
+    offset: [usize; 2],
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl<'a, T> VMatrixViewMut<'a, T> {
+    pub fn as_view(&self) -> VMatrixView<'_, T> {
+        VMatrixView {
+            matrix: self.matrix,
+            orientation: self.orientation,
+            offset: self.offset,
+            rows: self.rows,
+            cols: self.cols,
+        }
+    }
+
+    pub fn get(&self, coord: [usize; 2]) -> Option<&T> {
+        self.as_view().get(coord)
+    }
+
+    pub fn get_mut(&mut self, coord: [usize; 2]) -> Option<&mut T> {
+        let coord = self.as_view().matrix_coord(coord)?;
+        self.matrix.get_mut(coord)
+    }
+
+    pub fn oriented_mut(self, orientation: Orientation) -> Self {
+        let [rows, cols] = orientation.view_dims([self.rows, self.cols]);
+        Self {
+            orientation: self.orientation.then(orientation),
+            rows,
+            cols,
+            ..self
+        }
+    }
+
+    pub fn view_mut(self, range: Range<[usize; 2]>) -> Self {
+        let (offset, [rows, cols]) =
+            self.orientation
+                .sub_view(self.offset, [self.rows, self.cols], range);
+        Self {
+            offset,
+            rows,
+            cols,
+            ..self
+        }
+    }
+
+    pub fn iter(&self) -> impl '_ + Iterator<Item = ([usize; 2], &T)> {
+        self.as_view().iter()
+    }
+
+    pub fn to_matrix(&self) -> VMatrix<T>
+    where
+        T: Clone,
+    {
+        self.as_view().to_matrix()
+    }
+}
+
+impl<T> Index<[usize; 2]> for VMatrixViewMut<'_, T> {
+    type Output = T;
+
+    fn index(&self, coord: [usize; 2]) -> &Self::Output {
+        self.get(coord).expect("Invalid coords")
+    }
+}
+
+impl<T> IndexMut<[usize; 2]> for VMatrixViewMut<'_, T> {
+    fn index_mut(&mut self, coord: [usize; 2]) -> &mut Self::Output {
+        self.get_mut(coord).expect("Invalid coord")
+    }
 }
 
 impl<T> Index<[usize; 2]> for VMatrix<T> {
@@ -77,32 +77,21 @@ impl Display for Input {
 
 pub fn generator(input: &str) -> Input {
     let mut start = None;
-    let map = input
-        .lines()
-        .enumerate()
-        .flat_map(|(row, line)| {
-            line.chars()
-                .enumerate()
-                .map(|(col, c)| {
-                    Some(match c {
-                        'S' => {
-                            start = Some([row, col]);
-                            Pipe::Ground
-                        }
-                        '.' => Pipe::Ground,
-                        '|' => Pipe::Vertical,
-                        '-' => Pipe::Horizontal,
-                        'L' => Pipe::BendNE,
-                        'J' => Pipe::BendNW,
-                        '7' => Pipe::BendSW,
-                        'F' => Pipe::BendSE,
-                        _ => panic!("Bad input {c:?}"),
-                    })
-                })
-                .chain([None])
-                .collect::<Vec<_>>()
-        })
-        .collect();
+    let map = VMatrix::try_from_chars(input, |pos, ch| match ch {
+        'S' => {
+            start = Some(pos);
+            Ok(Pipe::Ground)
+        }
+        '.' => Ok(Pipe::Ground),
+        '|' => Ok(Pipe::Vertical),
+        '-' => Ok(Pipe::Horizontal),
+        'L' => Ok(Pipe::BendNE),
+        'J' => Ok(Pipe::BendNW),
+        '7' => Ok(Pipe::BendSW),
+        'F' => Ok(Pipe::BendSE),
+        _ => Err("unknown pipe"),
+    })
+    .unwrap_or_else(|err| panic!("{err}"));
     Input {
         map,
         start: start.unwrap(),
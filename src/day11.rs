@@ -1,41 +1,32 @@
-use std::collections::{BTreeMap, BTreeSet};
-
 use itertools::Itertools;
 
-pub fn generator(input: &str) -> Vec<[usize; 2]> {
-    input
-        .lines()
-        .enumerate()
-        .flat_map(|(row, line)| {
-            line.chars()
-                .enumerate()
-                .filter_map(move |(col, ch)| match ch {
-                    '.' => None,
-                    '#' => Some([row, col]),
-                    _ => panic!("Cannot handle {ch:?}"),
-                })
-        })
-        .collect()
+use crate::common::vmatrix::VMatrix;
+
+pub fn generator(input: &str) -> VMatrix<bool> {
+    VMatrix::try_from_chars(input, |_, ch| match ch {
+        '.' => Ok(false),
+        '#' => Ok(true),
+        _ => Err("unknown space"),
+    })
+    .unwrap_or_else(|err| panic!("{err}"))
 }
 
-fn gen_expansion_mapping(
-    values: impl Iterator<Item = usize>,
+// Where each row or column ends up once the ones without galaxies are expanded
+fn expanded_positions<'a>(
+    lines: impl Iterator<Item = impl Iterator<Item = &'a bool>>,
     expansion_multiplier: usize,
-) -> BTreeMap<usize, usize> {
-    let used: BTreeSet<usize> = values.collect();
-    let mut it = used.iter().copied();
-    let Some(first) = it.next() else {
-        return Default::default();
-    };
-    let mut prev = first;
-    let mut expansion = 0;
-    [(first, first)]
-        .into_iter()
-        .chain(it.map(|value| {
-            expansion += (value - prev - 1) * (expansion_multiplier - 1);
-            prev = value;
-            (value, value + expansion)
-        }))
+) -> Vec<usize> {
+    let mut position = 0;
+    lines
+        .map(|mut line| {
+            let expanded = position;
+            position += if line.any(|&galaxy| galaxy) {
+                1
+            } else {
+                expansion_multiplier
+            };
+            expanded
+        })
         .collect()
 }
 
@@ -46,12 +37,13 @@ fn distance(this: [usize; 2], that: [usize; 2]) -> usize {
         .sum()
 }
 
-fn solve_with_expansion_multiplier(input: &[[usize; 2]], expansion_multiplier: usize) -> usize {
-    let expansion_mappings = [0, 1]
-        .map(|i| gen_expansion_mapping(input.iter().map(|coord| coord[i]), expansion_multiplier));
+fn solve_with_expansion_multiplier(input: &VMatrix<bool>, expansion_multiplier: usize) -> usize {
+    let row_positions = expanded_positions(input.rows_iter(), expansion_multiplier);
+    let col_positions = expanded_positions(input.cols_iter(), expansion_multiplier);
     let expanded = input
         .iter()
-        .map(|coord| [0, 1].map(|i| expansion_mappings[i][&coord[i]]))
+        .filter(|(_, galaxy)| **galaxy)
+        .map(|([row, col], _)| [row_positions[row], col_positions[col]])
         .collect_vec();
     expanded
         .iter()
@@ -65,10 +57,10 @@ fn solve_with_expansion_multiplier(input: &[[usize; 2]], expansion_multiplier: u
         .sum()
 }
 
-pub fn part_1(input: &[[usize; 2]]) -> usize {
+pub fn part_1(input: &VMatrix<bool>) -> usize {
     solve_with_expansion_multiplier(input, 2)
 }
 
-pub fn part_2(input: &[[usize; 2]]) -> usize {
+pub fn part_2(input: &VMatrix<bool>) -> usize {
     solve_with_expansion_multiplier(input, 1_000_000)
 }
@@ -1,6 +1,6 @@
 use std::fmt::{Display, Write as _};
 
-use crate::common::vmatrix::VMatrix;
+use crate::common::vmatrix::{Orientation, VMatrix, VMatrixView};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Terrain {
@@ -12,17 +12,12 @@ pub fn generator(input: &str) -> Vec<VMatrix<Terrain>> {
     input
         .split("\n\n")
         .map(|pat| {
-            pat.lines()
-                .flat_map(|line| {
-                    line.chars()
-                        .map(|c| match c {
-                            '.' => Some(Terrain::Ash),
-                            '#' => Some(Terrain::Rocks),
-                            _ => panic!(),
-                        })
-                        .chain([None])
-                })
-                .collect()
+            VMatrix::try_from_chars(pat, |_, ch| match ch {
+                '.' => Ok(Terrain::Ash),
+                '#' => Ok(Terrain::Rocks),
+                _ => Err("unknown terrain"),
+            })
+            .unwrap_or_else(|err| panic!("{err}"))
         })
         .collect()
 }
@@ -39,90 +34,42 @@ impl Display for VMatrix<Terrain> {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Dimension {
-    Rows,
-    Cols,
-}
-
-impl VMatrix<Terrain> {
-    fn compare_impl(
-        &self,
-        length: usize,
-        pred: impl Fn(usize) -> bool,
-        allowed_smudges: &mut usize,
-    ) -> bool {
-        (0..length).all(|i| {
-            if pred(i) {
-                true
-            } else if 0 < *allowed_smudges {
-                *allowed_smudges -= 1;
-                true
-            } else {
-                false
-            }
-        })
-    }
-
-    fn compare(
-        &self,
-        dimension: Dimension,
-        i: usize,
-        j: usize,
-        allowed_smudges: &mut usize,
-    ) -> bool {
-        match dimension {
-            Dimension::Rows => {
-                self.compare_impl(self.cols, |c| self[[i, c]] == self[[j, c]], allowed_smudges)
-            }
-            Dimension::Cols => {
-                self.compare_impl(self.rows, |r| self[[r, i]] == self[[r, j]], allowed_smudges)
-            }
-        }
-    }
-
-    fn size_on(&self, dimension: Dimension) -> usize {
-        match dimension {
-            Dimension::Rows => self.rows,
-            Dimension::Cols => self.cols,
-        }
-    }
-
-    fn check_for_reflection_at(
-        &self,
-        dimension: Dimension,
-        i: usize,
-        mut allowed_smudges: usize,
-    ) -> bool {
-        let lower = 0..=i;
-        let higher = (i + 1)..self.size_on(dimension);
-        let found = lower
+// Checks rows only - columns are checked on the transposed view
+fn find_reflection(pattern: VMatrixView<Terrain>, allowed_smudges: usize) -> Option<usize> {
+    (1..pattern.rows).find(|&split| {
+        let smudges: usize = (0..split)
             .rev()
-            .zip(higher)
-            .all(|(i, j)| self.compare(dimension, i, j, &mut allowed_smudges));
-        found && allowed_smudges == 0
-    }
+            .zip(split..pattern.rows)
+            .map(|(above, below)| {
+                pattern
+                    .row(above)
+                    .zip(pattern.row(below))
+                    .filter(|(a, b)| a != b)
+                    .count()
+            })
+            .sum();
+        smudges == allowed_smudges
+    })
+}
 
-    fn find_reflection(&self, dimension: Dimension, allowed_smudges: usize) -> Option<usize> {
-        (0..(self.size_on(dimension) - 1))
-            .find(|i| self.check_for_reflection_at(dimension, *i, allowed_smudges))
+fn calc(pattern: &VMatrix<Terrain>, allowed_smudges: usize) -> usize {
+    if let Some(cols_before) =
+        find_reflection(pattern.oriented(Orientation::TRANSPOSE), allowed_smudges)
+    {
+        return cols_before;
     }
-
-    fn calc(&self, allowed_smudges: usize) -> usize {
-        if let Some(along_cols) = self.find_reflection(Dimension::Cols, allowed_smudges) {
-            return along_cols + 1;
-        }
-        if let Some(along_rows) = self.find_reflection(Dimension::Rows, allowed_smudges) {
-            return 100 * (along_rows + 1);
-        }
-        panic!()
+    if let Some(rows_before) =
+        find_reflection(pattern.oriented(Orientation::IDENTITY), allowed_smudges)
+    {
+        return 100 * rows_before;
     }
+    panic!("No reflection found")
 }
 
 pub fn part_1(input: &[VMatrix<Terrain>]) -> usize {
-    input.iter().map(|pat| pat.calc(0)).sum()
+    input.iter().map(|pat| calc(pat, 0)).sum()
 }
 
 pub fn part_2(input: &[VMatrix<Terrain>]) -> usize {
-    input.iter().map(|pat| pat.calc(1)).sum()
+    input.iter().map(|pat| calc(pat, 1)).sum()
 }
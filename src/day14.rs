@@ -1,8 +1,5 @@
-use std::collections::HashMap;
-
-use itertools::Itertools;
-
-use crate::common::vmatrix::VMatrix;
+use crate::common::cycle::{state_after, CycleDetection};
+use crate::common::vmatrix::{Orientation, VMatrix, VMatrixViewMut};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub enum Tile {
@@ -22,123 +19,64 @@ impl From<&Tile> for char {
 }
 
 pub fn generator(input: &str) -> VMatrix<Tile> {
-    VMatrix::from_chars(input, |_, ch| match ch {
-        '.' => Tile::Empty,
-        '#' => Tile::Cube,
-        'O' => Tile::Rounded,
-        _ => panic!(),
+    VMatrix::try_from_chars(input, |_, ch| match ch {
+        '.' => Ok(Tile::Empty),
+        '#' => Ok(Tile::Cube),
+        'O' => Ok(Tile::Rounded),
+        _ => Err("unknown tile"),
     })
+    .unwrap_or_else(|err| panic!("{err}"))
 }
 
-impl VMatrix<Tile> {
-    fn gen_four_ranges(&self) -> Vec<Vec<Vec<[usize; 2]>>> {
-        vec![
-            // North:
-            (0..self.cols)
-                .map(move |col| (0..self.rows).map(move |row| [row, col]).collect_vec())
-                .collect_vec(),
-            // West:
-            (0..self.rows)
-                .map(move |row| (0..self.cols).map(move |col| [row, col]).collect_vec())
-                .collect_vec(),
-            // South:
-            (0..self.cols)
-                .map(move |col| {
-                    (0..self.rows)
-                        .rev()
-                        .map(move |row| [row, col])
-                        .collect_vec()
-                })
-                .collect_vec(),
-            // East:
-            (0..self.rows)
-                .map(move |row| {
-                    (0..self.cols)
-                        .rev()
-                        .map(move |col| [row, col])
-                        .collect_vec()
-                })
-                .collect_vec(),
-        ]
-    }
+// Tilting any of these views north tilts the matrix in the order of the spin cycle
+const SPIN_CYCLE: [Orientation; 4] = [
+    Orientation::IDENTITY,      // North
+    Orientation::TRANSPOSE,     // West
+    Orientation::FLIP_VERTICAL, // South
+    Orientation::ROTATE_270,    // East
+];
 
-    fn tilt(&mut self, ranges: &[Vec<[usize; 2]>]) {
-        for range in ranges.iter() {
-            let mut range = range.iter().copied();
-            let mut pile_from = range.clone();
-            let mut num_rounded_to_pile = 0;
-            loop {
-                match range.next().map(|coord| (coord, self[coord])) {
-                    Some((_, Tile::Empty)) => {}
-                    Some((coord, Tile::Rounded)) => {
-                        num_rounded_to_pile += 1;
-                        self[coord] = Tile::Empty;
-                    }
-                    tile @ (Some((_, Tile::Cube)) | None) => {
-                        for coord in pile_from.take(num_rounded_to_pile) {
-                            self[coord] = Tile::Rounded;
-                        }
-                        if tile.is_some() {
-                            // Note that this will actually put us AFTER the tile we are matching
-                            // on. Which is what we want.
-                            pile_from = range.clone();
-                            num_rounded_to_pile = 0;
-                        } else {
-                            break;
-                        }
-                    }
+fn tilt_north(view: &mut VMatrixViewMut<Tile>) {
+    for col in 0..view.cols {
+        let mut pile_at = 0;
+        for row in 0..view.rows {
+            match view[[row, col]] {
+                Tile::Empty => {}
+                Tile::Cube => pile_at = row + 1,
+                Tile::Rounded => {
+                    view[[row, col]] = Tile::Empty;
+                    view[[pile_at, col]] = Tile::Rounded;
+                    pile_at += 1;
                 }
             }
         }
     }
+}
 
-    fn calc_load(&self) -> usize {
-        self.gen_four_ranges()[0]
-            .iter()
-            .flat_map(|range| {
-                range
-                    .iter()
-                    .rev()
-                    .enumerate()
-                    .filter_map(|(i, coord)| matches!(self[*coord], Tile::Rounded).then_some(i + 1))
-            })
-            .sum()
-    }
+fn calc_load(input: &VMatrix<Tile>) -> usize {
+    input
+        .iter()
+        .filter(|(_, tile)| matches!(tile, Tile::Rounded))
+        .map(|([row, _], _)| input.rows - row)
+        .sum()
 }
 
 pub fn part_1(input: &VMatrix<Tile>) -> usize {
     let mut input = input.clone();
-    input.tilt(&input.gen_four_ranges()[0]);
-    input.calc_load()
+    tilt_north(&mut input.oriented_mut(Orientation::IDENTITY));
+    calc_load(&input)
 }
 
 pub fn part_2(input: &VMatrix<Tile>) -> usize {
-    let mut input = input.clone();
-    let ranges_four_ways = input.gen_four_ranges();
-
-    let mut seen = HashMap::new();
-
-    const TIMES_TO_REPEAT: usize = 1_000_000_000;
-
-    for i in 0..TIMES_TO_REPEAT {
-        seen.insert(input.clone(), i);
-        for ranges in ranges_four_ways.iter() {
-            input.tilt(ranges);
-        }
-        if let Some(cycle_starts_at) = seen.get(&input) {
-            let cycle_will_be_repeated_at = i + 1; // at the next iteration
-            let steps_to_do_from_cycle_start = TIMES_TO_REPEAT - cycle_starts_at;
-            let cycle_length = cycle_will_be_repeated_at - cycle_starts_at;
-            let actual_steps_in_cycle = steps_to_do_from_cycle_start % cycle_length;
-            let use_the_one_from_step = actual_steps_in_cycle + cycle_starts_at;
-            let input_to_clone = seen
-                .iter()
-                .find_map(|(status, step)| (*step == use_the_one_from_step).then_some(status));
-            if let Some(input_to_clone) = input_to_clone {
-                input = input_to_clone.clone();
-                break;
+    let input = state_after(
+        input.clone(),
+        1_000_000_000,
+        CycleDetection::Brent,
+        |input| {
+            for orientation in SPIN_CYCLE {
+                tilt_north(&mut input.oriented_mut(orientation));
             }
-        }
-    }
-    input.calc_load()
+        },
+    );
+    calc_load(&input)
 }
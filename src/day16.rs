@@ -43,14 +43,15 @@ impl From<&Tile> for char {
 }
 
 pub fn generator(input: &str) -> VMatrix<Tile> {
-    VMatrix::from_chars(input, |_, ch| match ch {
-        '.' => Tile::Empty,
-        '/' => Tile::Mirror(MirrorDirection::Forward),
-        '\\' => Tile::Mirror(MirrorDirection::Backward),
-        '|' => Tile::Splitter(SplitterDirection::Vertical),
-        '-' => Tile::Splitter(SplitterDirection::Horizontal),
-        _ => panic!("Bad char {ch:?}"),
+    VMatrix::try_from_chars(input, |_, ch| match ch {
+        '.' => Ok(Tile::Empty),
+        '/' => Ok(Tile::Mirror(MirrorDirection::Forward)),
+        '\\' => Ok(Tile::Mirror(MirrorDirection::Backward)),
+        '|' => Ok(Tile::Splitter(SplitterDirection::Vertical)),
+        '-' => Ok(Tile::Splitter(SplitterDirection::Horizontal)),
+        _ => Err("unknown tile"),
     })
+    .unwrap_or_else(|err| panic!("{err}"))
 }
 
 impl MirrorDirection {
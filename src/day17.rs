@@ -1,11 +1,15 @@
-use crate::common::bfs::HashMapBfs;
+use crate::common::bfs::{manhattan_heuristic, HasCoord};
 use crate::common::direction::Direction;
+use crate::common::search::{search_buckets, SearchProblem};
 use crate::common::vmatrix::VMatrix;
 
 pub fn generator(input: &str) -> VMatrix<usize> {
-    VMatrix::from_chars(input, |_, ch| {
-        ch.is_ascii_digit().then_some(ch as usize).unwrap() - '0' as usize
+    VMatrix::try_from_chars(input, |_, ch| {
+        ch.to_digit(10)
+            .map(|digit| digit as usize)
+            .ok_or("not a digit")
     })
+    .unwrap_or_else(|err| panic!("{err}"))
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -15,54 +19,83 @@ struct State {
     direction: Direction,
 }
 
-fn solve(
-    input: &VMatrix<usize>,
-    can_go_straight: impl Fn(usize) -> bool,
-    can_turn: impl Fn(usize) -> bool,
-) -> usize {
-    let mut bfs = HashMapBfs::default();
-    for direction in [Direction::East, Direction::South] {
-        bfs.add_root(
-            State {
+impl HasCoord for State {
+    fn coord(&self) -> [usize; 2] {
+        self.coord
+    }
+}
+
+struct Crucible<'a, S, T> {
+    input: &'a VMatrix<usize>,
+    can_go_straight: S,
+    can_turn: T,
+}
+
+impl<S, T> SearchProblem for Crucible<'_, S, T>
+where
+    S: Fn(usize) -> bool,
+    T: Fn(usize) -> bool,
+{
+    type State = State;
+    type Cost = usize;
+
+    fn initial_states(&self) -> impl Iterator<Item = State> {
+        [Direction::East, Direction::South]
+            .into_iter()
+            .map(|direction| State {
                 coord: [0, 0],
                 steps: 100, // to force a turn
                 direction,
-            },
-            0,
-        );
+            })
     }
 
-    while let Some(state) = bfs.consider_next() {
-        if state.coord == [input.rows - 1, input.cols - 1] && can_turn(state.steps + 1) {
-            return *bfs.cost(&state).unwrap();
-        }
-
+    fn successors(&self, state: &State) -> impl Iterator<Item = (State, usize)> {
         let straight_option =
-            can_go_straight(state.steps + 1).then_some((state.direction, state.steps + 1));
-        let turn_options = can_turn(state.steps + 1).then_some([
+            (self.can_go_straight)(state.steps + 1).then_some((state.direction, state.steps + 1));
+        let turn_options = (self.can_turn)(state.steps + 1).then_some([
             (state.direction.clockwise(), 0),
             (state.direction.counter_clockwise(), 0),
         ]);
 
-        for (direction, steps) in straight_option
+        let coord = state.coord;
+        straight_option
             .into_iter()
             .chain(turn_options.into_iter().flatten())
-        {
-            if let Ok(coord) = input.motion(state.coord, direction.motion()) {
-                bfs.add_edge(
-                    state.clone(),
+            .filter_map(move |(direction, steps)| {
+                let coord = self.input.motion(coord, direction.motion()).ok()?;
+                Some((
                     State {
                         coord,
                         steps,
                         direction,
                     },
-                    input[coord],
-                );
-            }
-        }
+                    self.input[coord],
+                ))
+            })
+    }
+
+    fn is_goal(&self, state: &State) -> bool {
+        state.coord == [self.input.rows - 1, self.input.cols - 1]
+            && (self.can_turn)(state.steps + 1)
     }
 
-    panic!("No path found");
+    fn heuristic(&self, state: &State) -> usize {
+        manhattan_heuristic([self.input.rows - 1, self.input.cols - 1])(state)
+    }
+}
+
+fn solve(
+    input: &VMatrix<usize>,
+    can_go_straight: impl Fn(usize) -> bool,
+    can_turn: impl Fn(usize) -> bool,
+) -> usize {
+    let crucible = Crucible {
+        input,
+        can_go_straight,
+        can_turn,
+    };
+    // Every step costs between 1 and 9, so a bucket per cost beats a heap
+    search_buckets(&crucible).cost.expect("No path found")
 }
 
 pub fn part_1(input: &VMatrix<usize>) -> usize {
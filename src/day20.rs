@@ -1,10 +1,10 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 
 use itertools::Itertools;
-use num::Integer;
 use regex::Regex;
 
 use crate::common::bfs::HashMapBfs;
+use crate::common::number_theory::{first_common_hit, PeriodicHits};
 
 #[derive(Debug, Clone)]
 pub struct ModuleSpec {
@@ -116,11 +116,29 @@ impl State {
         }
     }
 
+    // Everything that decides what the modules will send from now on
+    fn modules_state(&self, names: &[String]) -> Vec<PulseType> {
+        names
+            .iter()
+            .flat_map(|name| match self.topology[name].module_type {
+                ModuleType::Interface => Vec::new(),
+                ModuleType::FlipFlop => vec![self.pulse_from(name)],
+                ModuleType::Conjunction => self.conjunctions[name]
+                    .iter()
+                    .sorted_by_key(|(input, _)| *input)
+                    .map(|(_, pulse_type)| *pulse_type)
+                    .collect(),
+            })
+            .collect()
+    }
+
+    // The listener is called for each pulse once its target has updated its state, before the
+    // target sends anything
     fn send_pulse(
         &mut self,
         target: &str,
         pulse_type: PulseType,
-        mut listener: impl FnMut(&str, PulseType, &str),
+        mut listener: impl FnMut(&Self, &str, PulseType, &str),
     ) -> [usize; 2] {
         let mut pulse_queue = VecDeque::<(&str, PulseType, &str)>::new();
         pulse_queue.push_back(("", pulse_type, target));
@@ -128,34 +146,35 @@ impl State {
         let mut counts = [0, 0];
 
         while let Some((source, pulse_type, target)) = pulse_queue.pop_front() {
-            listener(source, pulse_type, target);
             counts[pulse_type.idx()] += 1;
             let Some(module) = self.topology.get(target) else {
+                listener(self, source, pulse_type, target);
                 continue;
             };
             let out_pulse = match module.module_type {
-                ModuleType::Interface => pulse_type, // just resend the pulse
+                ModuleType::Interface => Some(pulse_type), // just resend the pulse
                 ModuleType::FlipFlop => {
                     match pulse_type {
                         PulseType::Low => {
                             let flip_flop = self.flip_flops.get_mut(target).unwrap();
                             *flip_flop = !*flip_flop;
-                            self.pulse_from(target)
-                        }
-                        PulseType::High => {
-                            // Flip flops ignore high pulses and do not retransmit anything
-                            continue;
+                            Some(self.pulse_from(target))
                         }
+                        // Flip flops ignore high pulses and do not retransmit anything
+                        PulseType::High => None,
                     }
                 }
                 ModuleType::Conjunction => {
                     let conjunction = self.conjunctions.get_mut(target).unwrap();
                     *conjunction.get_mut(source).unwrap() = pulse_type;
-                    self.pulse_from(target)
+                    Some(self.pulse_from(target))
                 }
             };
-            for output in module.outputs.iter() {
-                pulse_queue.push_back((target, out_pulse, output));
+            listener(self, source, pulse_type, target);
+            if let Some(out_pulse) = out_pulse {
+                for output in module.outputs.iter() {
+                    pulse_queue.push_back((target, out_pulse, output));
+                }
             }
         }
 
@@ -167,7 +186,7 @@ pub fn part_1(input: &[ModuleSpec]) -> usize {
     let mut state = State::new(input);
     let mut total_counts = [0; 2];
     for _ in 0..1000 {
-        let counts = state.send_pulse("broadcaster", PulseType::Low, |_, _, _| {});
+        let counts = state.send_pulse("broadcaster", PulseType::Low, |_, _, _, _| {});
         for (t, c) in total_counts.iter_mut().zip(counts) {
             *t += c;
         }
@@ -209,20 +228,24 @@ pub fn part_2(input: &[ModuleSpec]) -> usize {
 
     let mut state = State::new(input);
 
-    let mut deps = reverse_graph["rx"].clone();
-    for _i in 0.. {
-        let new_deps = deps
-            .iter()
-            .flat_map(|d| reverse_graph[d].iter().cloned())
-            .collect_vec();
-        if 4 < new_deps.len() {
-            break;
-        }
-        deps = new_deps.clone();
-    }
+    // This is not a general solver - it relies on the shape of the actual inputs:
+    //
+    // * rx is fed by a single conjunction, which sends it a low pulse when its memory is high for
+    //   all its inputs at once.
+    // * For each of the conjunction's inputs, the modules upstream of it get back to an earlier
+    //   state within a reasonable number of presses.
+    // * If the conjunction's memory is high for every input at some point of the same press, it is
+    //   high for all of them at the same point. The counters in the actual inputs all send their
+    //   high pulse in the same wave of the press, and only go back to low in a later one.
+    let [last_conjunction] = reverse_graph["rx"].as_slice() else {
+        panic!("Expected a single module to feed rx");
+    };
+    assert_eq!(
+        state.topology[last_conjunction].module_type,
+        ModuleType::Conjunction
+    );
+    let deps = &reverse_graph[last_conjunction];
 
-    // NOTE: the assumption is that such relevant subclusters need to all send high signal. This is
-    // just how the input is structured.
     let relevant_subclusters = deps
         .iter()
         .map(|d| {
@@ -231,39 +254,49 @@ pub fn part_2(input: &[ModuleSpec]) -> usize {
             c
         })
         .collect_vec();
+    // The conjunction's memory of the dep is part of the dep's state as well
+    let subcluster_state = |state: &State, dep_idx: usize| {
+        let mut cluster_state = state.modules_state(&relevant_subclusters[dep_idx]);
+        cluster_state.push(state.conjunctions[last_conjunction][&deps[dep_idx]]);
+        cluster_state
+    };
 
-    let mut visited_states = relevant_subclusters
-        .iter()
-        .map(|_| HashMap::<Vec<PulseType>, usize>::new())
+    // The state after each press, starting with no presses at all
+    let mut visited_states = (0..deps.len())
+        .map(|dep_idx| HashMap::from([(subcluster_state(&state, dep_idx), 0)]))
         .collect_vec();
-    let mut detected_cycles: Vec<Option<usize>> =
-        relevant_subclusters.iter().map(|_| None).collect_vec();
-    for i in 0.. {
-        let mut result = Some(());
-        state.send_pulse(
-            "broadcaster",
-            PulseType::Low,
-            |_source, pulse_type, target| {
-                if target == "rx" && pulse_type == PulseType::Low {
-                    result = None;
+    // A press, and the first later press that leaves the same state
+    let mut detected_cycles: Vec<Option<(usize, usize)>> = vec![None; deps.len()];
+    // The presses during which the conjunction's memory is high for the dep
+    let mut hits = vec![Vec::new(); deps.len()];
+    for press in 1.. {
+        state.send_pulse("broadcaster", PulseType::Low, |state, _, _, target| {
+            if target != last_conjunction {
+                return;
+            }
+            for (dep_idx, dep) in deps.iter().enumerate() {
+                if state.conjunctions[last_conjunction][dep] == PulseType::High
+                    && hits[dep_idx].last() != Some(&press)
+                {
+                    hits[dep_idx].push(press);
                 }
-            },
-        );
+            }
+        });
 
-        for (cluster, (visited, detected)) in relevant_subclusters
-            .iter()
-            .zip(visited_states.iter_mut().zip(detected_cycles.iter_mut()))
+        for (dep_idx, (visited, detected)) in visited_states
+            .iter_mut()
+            .zip(detected_cycles.iter_mut())
+            .enumerate()
         {
-            let cluster_state = cluster.iter().map(|m| state.pulse_from(m)).collect_vec();
-            match visited.entry(cluster_state) {
+            if detected.is_some() {
+                continue;
+            }
+            match visited.entry(subcluster_state(&state, dep_idx)) {
                 std::collections::hash_map::Entry::Occupied(entry) => {
-                    if detected.is_none() {
-                        assert_eq!(*entry.get(), 0);
-                        *detected = Some(i);
-                    }
+                    *detected = Some((*entry.get(), press));
                 }
                 std::collections::hash_map::Entry::Vacant(entry) => {
-                    entry.insert(i);
+                    entry.insert(press);
                 }
             }
         }
@@ -271,10 +304,26 @@ pub fn part_2(input: &[ModuleSpec]) -> usize {
             break;
         }
     }
-    let mut result = 1;
-    for cycle in detected_cycles {
-        // I don't know why it's always at the end of the cycle, but for some reason it does.
-        result = result.lcm(&cycle.unwrap());
-    }
-    result
+
+    // The state after press `repeated_at` is the same as after press `first_seen`, so every press
+    // after `first_seen` is repeated `repeated_at - first_seen` presses later
+    let all_hits = hits
+        .into_iter()
+        .zip(detected_cycles)
+        .map(|(hits, cycle)| {
+            let (first_seen, repeated_at) = cycle.unwrap();
+            let cycle_start = first_seen + 1;
+            let (prefix_hits, cycle_hits) = hits
+                .into_iter()
+                .filter(|press| *press <= repeated_at)
+                .partition(|press| *press < cycle_start);
+            PeriodicHits {
+                prefix_hits,
+                cycle_start,
+                period: repeated_at - first_seen,
+                cycle_hits,
+            }
+        })
+        .collect_vec();
+    first_common_hit(&all_hits).expect("rx never gets a low pulse")
 }
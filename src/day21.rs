@@ -4,7 +4,7 @@ use std::fmt::Display;
 use itertools::Itertools;
 use num::Integer;
 
-use crate::common::bfs::HashMapBfs;
+use crate::common::bfs::{HashMapBfs, ParallelBfs};
 use crate::common::direction::Direction;
 use crate::common::vmatrix::VMatrix;
 
@@ -47,16 +47,19 @@ impl From<&Tile> for char {
 
 pub fn generator(input: &str) -> Input {
     let mut start = None;
-    let garden = VMatrix::from_chars(input, |pos, ch| match ch {
+    let garden = VMatrix::try_from_chars(input, |pos, ch| match ch {
         'S' => {
-            assert!(start.is_none());
+            if start.is_some() {
+                return Err("second start");
+            }
             start = Some(pos);
-            Tile::Plot
+            Ok(Tile::Plot)
         }
-        '.' => Tile::Plot,
-        '#' => Tile::Rock,
-        _ => panic!("Bad char {ch:?}"),
-    });
+        '.' => Ok(Tile::Plot),
+        '#' => Ok(Tile::Rock),
+        _ => Err("unknown tile"),
+    })
+    .unwrap_or_else(|err| panic!("{err}"));
     Input {
         garden,
         start: start.unwrap(),
@@ -87,29 +90,29 @@ pub fn part_1(input: &Input) -> usize {
 
 pub fn part_2(input: &Input) -> usize {
     let total_steps = 26501365;
-    let mut bfs = HashMapBfs::default();
-    bfs.add_root(([0, 0], input.start), 0);
-    while let Some(parent) = bfs.consider_next() {
-        let (instance, coord) = parent;
-        for direction in Direction::ALL {
-            let motion = direction.motion();
-            match input.garden.motion(coord, motion) {
-                Ok(step_to) => {
-                    if input.garden[step_to] == Tile::Plot {
-                        bfs.add_edge(parent, (instance, step_to), 1);
+    let bfs = ParallelBfs::run(
+        [([0, 0], input.start)],
+        |&(instance, coord): &([isize; 2], [usize; 2])| {
+            Direction::ALL.into_iter().filter_map(move |direction| {
+                let motion = direction.motion();
+                match input.garden.motion(coord, motion) {
+                    Ok(step_to) => {
+                        (input.garden[step_to] == Tile::Plot).then_some((instance, step_to))
                     }
-                }
-                Err(step_to) => {
-                    if input.garden[step_to] == Tile::Plot {
-                        let new_instance = [instance[0] + motion[0], instance[1] + motion[1]];
-                        if new_instance.iter().all(|coord| coord.abs() <= 2) {
-                            bfs.add_edge(parent, (new_instance, step_to), 1);
+                    Err(step_to) => {
+                        if input.garden[step_to] != Tile::Plot {
+                            return None;
                         }
+                        let new_instance = [instance[0] + motion[0], instance[1] + motion[1]];
+                        new_instance
+                            .iter()
+                            .all(|coord| coord.abs() <= 2)
+                            .then_some((new_instance, step_to))
                     }
                 }
-            }
-        }
-    }
+            })
+        },
+    );
 
     println!(
         "Num instances {}",
@@ -4,6 +4,8 @@ use std::ops::Range;
 use itertools::Itertools;
 use regex::Regex;
 
+use crate::common::dominators::DominatorTree;
+
 #[derive(Debug, Clone)]
 pub struct Brick {
     start: [usize; 3],
@@ -119,37 +121,28 @@ pub fn part_1(input: &[Brick]) -> usize {
 
 pub fn part_2(input: &[Brick]) -> usize {
     let supported_by_grap = compute_supported_by_graph(input);
+    // A brick falls when everything under it is gone, which makes it one of the bricks dominated by
+    // the disintegrated one - as long as the bricks on the floor are all supported by "the ground".
+    let ground = input.len();
     let mut supporting_graph = HashMap::<usize, Vec<usize>>::new();
-    for (supporter, supports) in supported_by_grap.iter() {
-        for support in supports {
-            supporting_graph
-                .entry(*support)
-                .or_default()
-                .push(*supporter);
-        }
-    }
-
-    let num_affected_when_disintegrating = |disintegrated_brick_idx: usize| -> usize {
-        let mut to_disintegrate = vec![disintegrated_brick_idx];
-        let mut disintegrated = HashSet::<usize>::new();
-
-        while let Some(idx) = to_disintegrate.pop() {
-            disintegrated.insert(idx);
-            let Some(affected_list) = supporting_graph.get(&idx) else {
-                continue;
-            };
-            for affected in affected_list {
-                if supported_by_grap[affected]
-                    .iter()
-                    .all(|i| disintegrated.contains(i))
-                {
-                    to_disintegrate.push(*affected);
+    for brick_idx in 0..input.len() {
+        match supported_by_grap.get(&brick_idx) {
+            Some(supporters) => {
+                for supporter in supporters {
+                    supporting_graph
+                        .entry(*supporter)
+                        .or_default()
+                        .push(brick_idx);
                 }
             }
+            None => {
+                supporting_graph.entry(ground).or_default().push(brick_idx);
+            }
         }
+    }
 
-        disintegrated.len() - 1
-    };
-
-    (0..input.len()).map(num_affected_when_disintegrating).sum()
+    let dominator_tree = DominatorTree::new(&supporting_graph, ground);
+    (0..input.len())
+        .map(|brick_idx| dominator_tree.subtree_size(&brick_idx).unwrap() - 1)
+        .sum()
 }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use itertools::Itertools;
 
@@ -29,15 +29,16 @@ impl From<&Tile> for char {
 }
 
 pub fn generator(input: &str) -> VMatrix<Tile> {
-    VMatrix::from_chars(input, |_pos, ch| match ch {
-        '.' => Tile::Paths,
-        '#' => Tile::Forset,
-        '^' => Tile::Slope(Direction::North),
-        'v' => Tile::Slope(Direction::South),
-        '<' => Tile::Slope(Direction::West),
-        '>' => Tile::Slope(Direction::East),
-        _ => panic!("Invalid tile definition {ch:?}"),
+    VMatrix::try_from_chars(input, |_pos, ch| match ch {
+        '.' => Ok(Tile::Paths),
+        '#' => Ok(Tile::Forset),
+        '^' => Ok(Tile::Slope(Direction::North)),
+        'v' => Ok(Tile::Slope(Direction::South)),
+        '<' => Ok(Tile::Slope(Direction::West)),
+        '>' => Ok(Tile::Slope(Direction::East)),
+        _ => Err("unknown tile"),
     })
+    .unwrap_or_else(|err| panic!("{err}"))
 }
 
 fn find_start_and_end_coords(input: &VMatrix<Tile>) -> ([usize; 2], [usize; 2]) {
@@ -117,20 +118,31 @@ fn solve(
     start: [usize; 2],
     end: [usize; 2],
 ) -> usize {
-    let mut dfs = HashMapDfs::new(start, 0);
-    std::iter::from_fn(|| {
-        let coord = dfs.consider_next()?;
+    // The rest of the hike can, at best, take the longest edge out of every node it has not been to
+    let longest_edges = graph
+        .iter()
+        .map(|(coord, edges)| (*coord, edges.values().copied().max().unwrap_or(0)))
+        .collect::<HashMap<[usize; 2], usize>>();
+    let mut dfs = HashMapDfs::new(start, 0).with_upper_bound(
+        |_coord: &[usize; 2], cost: &usize, in_path: &HashSet<[usize; 2]>| {
+            cost + longest_edges
+                .iter()
+                .filter(|(coord, _)| !in_path.contains(*coord))
+                .map(|(_, longest_edge)| longest_edge)
+                .sum::<usize>()
+        },
+    );
+    while let Some(coord) = dfs.consider_next() {
         if coord == end {
-            Some(dfs.cost(&coord))
+            let cost = dfs.cost(&coord);
+            dfs.offer_incumbent(cost);
         } else {
             for (neighbor, cost) in graph[&coord].iter() {
                 dfs.add_edge(&coord, *neighbor, *cost);
             }
-            Some(0) // can't be max
         }
-    })
-    .max()
-    .unwrap()
+    }
+    *dfs.incumbent().expect("No path to the end")
 }
 
 pub fn part_1(input: &VMatrix<Tile>) -> usize {
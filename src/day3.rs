@@ -2,6 +2,9 @@ use std::collections::HashMap;
 use std::fmt::Write;
 use std::ops::Range;
 
+use itertools::Itertools;
+
+use crate::common::stencil;
 use crate::common::vmatrix::VMatrix;
 
 #[derive(Debug)]
@@ -48,21 +51,19 @@ pub fn generator(input: &str) -> Map {
 impl Map {
     fn number_positions_in_row(&self, row: usize) -> impl '_ + Iterator<Item = Range<usize>> {
         let mut start = None;
-        (0..=(self.0.cols)).filter_map(move |col| {
-            let cell = if col == self.0.cols {
-                Cell::Empty
-            } else {
-                self.0[[row, col]]
-            };
-            match (cell, start) {
+        self.0
+            .row(row)
+            .copied()
+            .chain([Cell::Empty])
+            .enumerate()
+            .filter_map(move |(col, cell)| match (cell, start) {
                 (Cell::Digit(_), None) => {
                     start = Some(col);
                     None
                 }
                 (Cell::Empty | Cell::Symbol(_), Some(_)) => Some(start.take().unwrap()..col),
                 _ => None,
-            }
-        })
+            })
     }
 
     fn number_positions(&self) -> impl '_ + Iterator<Item = (usize, Range<usize>)> {
@@ -72,50 +73,21 @@ impl Map {
         })
     }
 
-    fn locations_around(&self, row: usize, cols: Range<usize>) -> impl Iterator<Item = [usize; 2]> {
-        let first_col = if 0 < cols.start {
-            Some(cols.start - 1)
-        } else {
-            None
-        };
-        let after_col = if cols.end + 1 < self.0.cols {
-            Some(cols.end + 1)
-        } else {
-            None
-        };
-        let cols = (first_col.unwrap_or(0))..(after_col.unwrap_or(self.0.cols));
-        let ranges: [Option<(usize, Range<usize>)>; 4] = [
-            if 0 < row {
-                Some((row - 1, cols.clone()))
-            } else {
-                None
-            },
-            first_col.map(|col| (row, col..(col + 1))),
-            after_col.map(|col| (row, (col - 1)..col)),
-            if row + 1 < self.0.rows {
-                Some((row + 1, cols))
-            } else {
-                None
-            },
-        ];
-        ranges
-            .into_iter()
-            .flatten()
-            .flat_map(|(row, cols)| cols.map(move |col| [row, col]))
-    }
-
     fn symbols_around_number(
         &self,
         row: usize,
         cols: Range<usize>,
     ) -> impl '_ + Iterator<Item = ([usize; 2], char)> {
-        self.locations_around(row, cols).filter_map(|pos| {
-            if let Cell::Symbol(s) = self.0[pos] {
-                Some((pos, s))
-            } else {
-                None
-            }
-        })
+        cols.flat_map(move |col| self.0.motion_values([row, col], stencil::EIGHT_NEIGHBORS))
+            .filter_map(|(coord, cell)| {
+                if let Cell::Symbol(s) = cell {
+                    Some((coord, *s))
+                } else {
+                    None
+                }
+            })
+            // A symbol can touch more than one digit of the same number
+            .unique()
     }
 
     fn number_near_symbols(&self, row: usize, cols: Range<usize>) -> bool {
@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 
 use itertools::Itertools;
-use num::Integer;
+
+use crate::common::cycle::{find_cycle, CycleDetection};
+use crate::common::number_theory::{first_common_hit, PeriodicHits};
 
 #[derive(Debug)]
 pub struct Input {
@@ -52,6 +54,31 @@ impl Input {
         })
     }
 
+    // The walk repeats once it is back at the same node at the same point in the directions
+    fn hits_from(&self, start: &str) -> PeriodicHits {
+        let cycle = find_cycle(
+            &(start, 0),
+            CycleDetection::Brent,
+            |(node, direction_idx): &mut (&str, usize)| {
+                *node = self
+                    .do_step(node, self.directions[*direction_idx])
+                    .expect("Walked to an unknown node");
+                *direction_idx = (*direction_idx + 1) % self.directions.len();
+            },
+        );
+        let (prefix_hits, cycle_hits) = self
+            .walk_from(start)
+            .take(cycle.prefix + cycle.length)
+            .positions(|node| node.ends_with('Z'))
+            .partition(|time| *time < cycle.prefix);
+        PeriodicHits {
+            prefix_hits,
+            cycle_start: cycle.prefix,
+            period: cycle.length,
+            cycle_hits,
+        }
+    }
+
     fn walk_from<'a>(&'a self, mut node: &'a str) -> impl 'a + Iterator<Item = &str> {
         self.gen_directions().map_while(move |direction| {
             let result = node;
@@ -72,39 +99,12 @@ pub fn part_1(input: &Input) -> usize {
         .count()
 }
 
-#[derive(Debug)]
-struct CycleDescr {
-    offset: usize,
-    length: usize,
-}
-
-fn join_cycles(mut first: CycleDescr, mut second: CycleDescr) -> CycleDescr {
-    if second.offset < first.offset {
-        std::mem::swap(&mut first, &mut second);
-    }
-    let mut offset = second.offset;
-    while (offset - first.offset) % first.length != 0 {
-        offset += second.length;
-    }
-    CycleDescr {
-        offset,
-        length: first.length.lcm(&second.length),
-    }
-}
-
 pub fn part_2(input: &Input) -> usize {
-    let cycles = input
+    let all_hits = input
         .nodes
         .keys()
-        .filter_map(|node| {
-            if !node.ends_with('A') {
-                return None;
-            }
-            let mut walk = input.walk_from(node);
-            let offset = walk.by_ref().take_while(|n| !n.ends_with('Z')).count();
-            let length = walk.take_while(|n| !n.ends_with('Z')).count() + 1;
-            Some(CycleDescr { offset, length })
-        })
+        .filter(|node| node.ends_with('A'))
+        .map(|node| input.hits_from(node))
         .collect_vec();
-    cycles.into_iter().reduce(join_cycles).unwrap().offset
+    first_common_hit(&all_hits).unwrap()
 }